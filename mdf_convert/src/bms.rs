@@ -0,0 +1,380 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+};
+
+use anyhow::bail;
+use mdf_schema::{MdfChart, Microseconds, NoteKind};
+
+/// Grid resolution of an exported measure (4/4), i.e. 48 slots per beat.
+const SLOTS_PER_MEASURE: u32 = 192;
+/// Largest two-digit base-36 id (`ZZ`).
+const MAX_ID: u16 = 36 * 36 - 1;
+
+const CH_BGM: u8 = 0x01;
+const CH_BPM_EXT: u8 = 0x08;
+
+/// Export an `MdfChart` as a 7-key single-play BMS (`.bme`) file.
+///
+/// - Tempo comes from `visual_events`, or from `analysis.bpm_timeline` when there are none
+///   (compiled charts need `CompileOptions::emit_analysis`); charts with neither are
+///   rejected. Every BPM change is written as `#BPMxx` + channel `08`, and measures are 4/4.
+/// - Times are quantized to 1/192 of a measure. Two notes on one lane that land in the
+///   same slot are an error.
+/// - Holds of every kind become `#LNTYPE 1` long notes: hell (HCN/HBSS) judging and
+///   MSS reverse checkpoints have no BMS equivalent and are dropped.
+/// - `sound_id`s are assigned `#WAVxx` ids in sorted order; notes without a sound use an
+///   id with no `#WAV` definition. `speed_events` are not exported.
+/// - BMS cannot play part of a file, so charts with `resource_slices` are rejected.
+pub fn export_bms(chart: &MdfChart) -> anyhow::Result<String> {
+    if let Some(id) = chart.resource_slices.keys().min() {
+        bail!("sliced keysound {id:?} cannot be exported to BMS");
+    }
+    let mut sound_ids: BTreeSet<&str> = chart.resources.keys().map(String::as_str).collect();
+    sound_ids.extend(chart.notes.iter().filter_map(|n| n.sound_id.as_deref()));
+    sound_ids.extend(chart.bgm_events.iter().map(|e| e.sound_id.as_str()));
+    if sound_ids.len() >= MAX_ID as usize {
+        bail!(
+            "too many sounds for BMS: {} (max {})",
+            sound_ids.len(),
+            MAX_ID - 1
+        );
+    }
+    let wav_id: BTreeMap<&str, u16> = sound_ids.iter().zip(1..).map(|(&s, i)| (s, i)).collect();
+    let silent_id = sound_ids.len() as u16 + 1;
+
+    let tempo = TempoMap::new(chart)?;
+    let mut grid = Grid::default();
+
+    let mut bpm_ids: Vec<f64> = Vec::new();
+    for &(time_us, bpm) in tempo.changes.iter().skip(1) {
+        let id = match bpm_ids.iter().position(|&b| b == bpm) {
+            Some(i) => i + 1,
+            None => {
+                bpm_ids.push(bpm);
+                bpm_ids.len()
+            }
+        };
+        if id > MAX_ID as usize {
+            bail!("too many BPM changes for BMS (max {MAX_ID})");
+        }
+        grid.place(tempo.slot(time_us), CH_BPM_EXT, id as u16, "BPM change")?;
+    }
+
+    for e in &chart.bgm_events {
+        grid.place_bgm(tempo.slot(e.time_us), wav_id[e.sound_id.as_str()]);
+    }
+
+    for (note_index, n) in chart.notes.iter().enumerate() {
+        let Some(&channel) = KEY_CHANNELS.get(n.col as usize) else {
+            bail!("lane out of range (note_index={note_index}, col={})", n.col);
+        };
+        let id = n.sound_id.as_deref().map_or(silent_id, |s| wav_id[s]);
+        let what = format!("note_index={note_index}, col={}", n.col);
+        match &n.kind {
+            NoteKind::Tap => grid.place(tempo.slot(n.time_us), channel, id, &what)?,
+            NoteKind::ChargeNote { end_time_us }
+            | NoteKind::HellChargeNote { end_time_us }
+            | NoteKind::BackSpinScratch { end_time_us }
+            | NoteKind::HellBackSpinScratch { end_time_us }
+            | NoteKind::MultiSpinScratch { end_time_us, .. }
+            | NoteKind::HellMultiSpinScratch { end_time_us, .. } => {
+                let ln = channel + 0x40;
+                let (start, end) = (tempo.slot(n.time_us), tempo.slot(*end_time_us));
+                if end <= start {
+                    bail!("long note shorter than the export grid ({what})");
+                }
+                grid.place(start, ln, id, &what)?;
+                grid.place(end, ln, id, &what)?;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "#PLAYER 1")?;
+    writeln!(out, "#TITLE {}", chart.meta.title)?;
+    writeln!(out, "#ARTIST {}", chart.meta.artist)?;
+    writeln!(out, "#BPM {}", tempo.changes[0].1)?;
+    writeln!(out, "#LNTYPE 1")?;
+    for (sound_id, &id) in &wav_id {
+        if let Some(path) = chart.resources.get(*sound_id) {
+            writeln!(out, "#WAV{} {path}", base36(id))?;
+        }
+    }
+    for (i, bpm) in bpm_ids.iter().enumerate() {
+        writeln!(out, "#BPM{} {bpm}", base36(i as u16 + 1))?;
+    }
+    writeln!(out)?;
+    for ((measure, channel, _), slots) in &grid.lines {
+        if *measure > 999 {
+            bail!("chart is too long for BMS (measure={measure}, max 999)");
+        }
+        writeln!(out, "#{measure:03}{channel:02X}:{}", render(slots))?;
+    }
+    Ok(out)
+}
+
+/// Channel per MDFS col (`S1234567`) for 1P: scratch is `16`, keys 6/7 are `18`/`19`.
+const KEY_CHANNELS: [u8; 8] = [0x16, 0x11, 0x12, 0x13, 0x14, 0x15, 0x18, 0x19];
+
+struct TempoMap {
+    /// (time_us, bpm), sorted by time. The first BPM also applies before its time.
+    changes: Vec<(Microseconds, f64)>,
+}
+
+impl TempoMap {
+    fn new(chart: &MdfChart) -> anyhow::Result<Self> {
+        let mut changes: Vec<(Microseconds, f64)> = chart
+            .visual_events
+            .iter()
+            .map(|e| (e.time_us, e.bpm))
+            .collect();
+        if changes.is_empty() {
+            if let Some(analysis) = &chart.analysis {
+                changes = analysis.bpm_timeline.iter().map(|p| (p.time_us, p.bpm)).collect();
+            }
+        }
+        changes.retain(|c| c.1 > 0.0);
+        if changes.is_empty() {
+            bail!("chart has no tempo: BMS export needs visual_events or analysis.bpm_timeline");
+        }
+        changes.sort_by_key(|c| c.0);
+        changes.dedup_by(|next, prev| next.1 == prev.1);
+        changes[0].0 = 0;
+        Ok(Self { changes })
+    }
+
+    /// Global grid slot (measure * 192 + position) for a time.
+    fn slot(&self, time_us: Microseconds) -> u32 {
+        let mut beats = 0.0;
+        for (i, &(start, bpm)) in self.changes.iter().enumerate() {
+            if time_us <= start {
+                break;
+            }
+            let end = self
+                .changes
+                .get(i + 1)
+                .map_or(time_us, |n| n.0.min(time_us));
+            beats += (end - start) as f64 / 1_000_000.0 * bpm / 60.0;
+        }
+        (beats * f64::from(SLOTS_PER_MEASURE) / 4.0).round() as u32
+    }
+}
+
+#[derive(Default)]
+struct Grid {
+    /// (measure, channel, BGM lane) -> object id per slot (0 = empty).
+    lines: BTreeMap<(u32, u8, usize), Vec<u16>>,
+}
+
+impl Grid {
+    fn line(&mut self, slot: u32, channel: u8, lane: usize) -> (&mut Vec<u16>, usize) {
+        let (measure, pos) = (slot / SLOTS_PER_MEASURE, slot % SLOTS_PER_MEASURE);
+        let line = self
+            .lines
+            .entry((measure, channel, lane))
+            .or_insert_with(|| vec![0; SLOTS_PER_MEASURE as usize]);
+        (line, pos as usize)
+    }
+
+    fn place(&mut self, slot: u32, channel: u8, id: u16, what: &str) -> anyhow::Result<()> {
+        let (line, pos) = self.line(slot, channel, 0);
+        if line[pos] != 0 {
+            bail!("objects collide after quantizing to 1/{SLOTS_PER_MEASURE} measures ({what})");
+        }
+        line[pos] = id;
+        Ok(())
+    }
+
+    /// BGM objects may overlap; each overlap goes onto another `01` line.
+    fn place_bgm(&mut self, slot: u32, id: u16) {
+        for lane in 0.. {
+            let (line, pos) = self.line(slot, CH_BGM, lane);
+            if line[pos] == 0 {
+                line[pos] = id;
+                return;
+            }
+        }
+    }
+}
+
+/// Object data at the coarsest resolution that keeps every slot.
+fn render(slots: &[u16]) -> String {
+    let step = slots
+        .iter()
+        .enumerate()
+        .filter(|(_, &id)| id != 0)
+        .fold(slots.len(), |g, (i, _)| gcd(g, i));
+    slots.iter().step_by(step).map(|&id| base36(id)).collect()
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn base36(id: u16) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let (hi, lo) = (id / 36, id % 36);
+    format!(
+        "{}{}",
+        DIGITS[hi as usize] as char, DIGITS[lo as usize] as char
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdf_schema::{BgmEvent, Metadata, Note, VisualEvent};
+    use std::collections::HashMap;
+
+    fn chart(notes: Vec<Note>, bpms: &[(Microseconds, f64)]) -> MdfChart {
+        MdfChart {
+            meta: Metadata {
+                title: "Song".to_string(),
+                artist: "Someone".to_string(),
+                ..Default::default()
+            },
+            resources: HashMap::from([
+                ("BGM".to_string(), "song.ogg".to_string()),
+                ("K01".to_string(), "kick.wav".to_string()),
+            ]),
+            visual_events: bpms
+                .iter()
+                .map(|&(time_us, bpm)| VisualEvent {
+                    time_us,
+                    bpm,
+                    is_measure_line: false,
+                    beat_n: 0,
+                    beat_d: 0,
+                })
+                .collect(),
+            notes,
+            bgm_events: vec![BgmEvent {
+                time_us: 0,
+                sound_id: "BGM".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn note(time_us: Microseconds, col: u8, kind: NoteKind, sound_id: Option<&str>) -> Note {
+        Note {
+            time_us,
+            col,
+            kind,
+            sound_id: sound_id.map(str::to_string),
+        }
+    }
+
+    fn data_lines(bms: &str) -> Vec<&str> {
+        bms.lines()
+            .filter(|l| l.as_bytes().get(1).is_some_and(u8::is_ascii_digit))
+            .collect()
+    }
+
+    #[test]
+    fn exports_header_taps_long_notes_and_bgm() {
+        let c = chart(
+            vec![
+                note(0, 1, NoteKind::Tap, Some("K01")),
+                note(
+                    0,
+                    6,
+                    NoteKind::HellChargeNote {
+                        end_time_us: 1_000_000,
+                    },
+                    None,
+                ),
+                note(500_000, 0, NoteKind::Tap, None),
+            ],
+            &[(0, 120.0)],
+        );
+        let bms = export_bms(&c).unwrap();
+        assert!(bms.starts_with("#PLAYER 1\n#TITLE Song\n#ARTIST Someone\n#BPM 120\n#LNTYPE 1\n"));
+        assert!(bms.contains("#WAV01 song.ogg\n#WAV02 kick.wav\n"));
+        assert_eq!(
+            data_lines(&bms),
+            vec!["#00001:01", "#00011:02", "#00016:00030000", "#00058:0303"]
+        );
+    }
+
+    #[test]
+    fn exports_bpm_changes_as_extended_bpm() {
+        // 120 BPM for one measure, then 180.5 BPM; the note sits one beat later
+        let c = chart(
+            vec![note(2_332_410, 7, NoteKind::Tap, None)],
+            &[(0, 120.0), (2_000_000, 180.5)],
+        );
+        let bms = export_bms(&c).unwrap();
+        assert!(bms.contains("#BPM 120\n"));
+        assert!(bms.contains("#BPM01 180.5\n"));
+        assert_eq!(
+            data_lines(&bms),
+            vec!["#00001:01", "#00108:01", "#00119:00030000"]
+        );
+    }
+
+    #[test]
+    fn overlapping_bgm_uses_extra_lines_but_notes_must_not_collide() {
+        let mut c = chart(vec![note(0, 1, NoteKind::Tap, None)], &[(0, 120.0)]);
+        c.bgm_events.push(BgmEvent {
+            time_us: 0,
+            sound_id: "K01".to_string(),
+        });
+        let bms = export_bms(&c).unwrap();
+        assert_eq!(
+            data_lines(&bms),
+            vec!["#00001:01", "#00001:02", "#00011:03"]
+        );
+
+        c.notes.push(note(1, 1, NoteKind::Tap, None));
+        let err = export_bms(&c).unwrap_err();
+        assert!(err.to_string().contains("objects collide"));
+        assert!(err.to_string().contains("note_index=1, col=1"));
+    }
+
+    #[test]
+    fn sliced_keysounds_are_rejected() {
+        let mut c = chart(vec![note(0, 1, NoteKind::Tap, Some("K01"))], &[(0, 120.0)]);
+        c.resource_slices.insert(
+            "K01".to_string(),
+            mdf_schema::SoundSlice {
+                start_ms: 0,
+                len_ms: 100,
+            },
+        );
+        let err = export_bms(&c).unwrap_err();
+        assert!(err.to_string().contains("\"K01\""));
+    }
+
+    #[test]
+    fn compiled_chart_takes_tempo_from_bpm_timeline() {
+        // 155 BPM, 16ths: one measure is 192 slots, so each step is exactly 12 slots
+        let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 155\n  @div 16\n  .N......\n  ..N.....\n  ........\n  ...N....\n";
+        let options = mdfs_compiler::CompileOptions {
+            emit_analysis: true,
+            ..Default::default()
+        };
+        let c = mdfs_compiler::compile_str_with_options(src, options).unwrap();
+        assert!(c.visual_events.is_empty());
+
+        let bms = export_bms(&c).unwrap();
+        assert!(bms.contains("#BPM 155\n"));
+        assert_eq!(
+            data_lines(&bms),
+            vec![
+                "#00011:01",
+                "#00012:00010000000000000000000000000000",
+                "#00013:00000001000000000000000000000000",
+            ]
+        );
+
+        let without_analysis = mdfs_compiler::compile_str(src).unwrap();
+        let err = export_bms(&without_analysis).unwrap_err();
+        assert!(err.to_string().contains("no tempo"));
+    }
+}
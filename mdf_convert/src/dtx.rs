@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use mdf_schema::{BgmEvent, MdfChart, Metadata, Note, NoteKind, VisualEvent};
+
+use crate::{finalize, Timing};
+
+/// Channel of the first drum pad (`11` = hi-hat close).
+const DRUM_CHANNEL_BASE: u8 = 0x11;
+const CH_BGM: u8 = 0x01;
+const CH_MEASURE_LENGTH: u8 = 0x02;
+const CH_BPM: u8 = 0x03;
+const CH_BPM_EXT: u8 = 0x08;
+
+/// DTXMania drum pads, in channel order (`11`-`1C`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrumPad {
+    HiHatClose,
+    Snare,
+    BassDrum,
+    HighTom,
+    LowTom,
+    Cymbal,
+    FloorTom,
+    HiHatOpen,
+    RideCymbal,
+    LeftCymbal,
+    LeftPedal,
+    LeftBassDrum,
+}
+
+impl DrumPad {
+    pub const ALL: [DrumPad; 12] = [
+        DrumPad::HiHatClose,
+        DrumPad::Snare,
+        DrumPad::BassDrum,
+        DrumPad::HighTom,
+        DrumPad::LowTom,
+        DrumPad::Cymbal,
+        DrumPad::FloorTom,
+        DrumPad::HiHatOpen,
+        DrumPad::RideCymbal,
+        DrumPad::LeftCymbal,
+        DrumPad::LeftPedal,
+        DrumPad::LeftBassDrum,
+    ];
+
+    fn from_channel(channel: u8) -> Option<Self> {
+        Self::ALL
+            .get(channel.checked_sub(DRUM_CHANNEL_BASE)? as usize)
+            .copied()
+    }
+}
+
+/// Drum pad → MDFS col mapping used by [`import_dtx`].
+///
+/// Pads mapped to `None` are not playable; their sounds are kept as BGM events so the
+/// keysounded mix stays intact. The same applies when several pads land on one col at
+/// the same time: the first object in the file becomes the note, the rest become BGM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtxLaneMap {
+    cols: [Option<u8>; 12],
+}
+
+impl DtxLaneMap {
+    /// Left cymbal on the scratch lane, the rest of the kit left-to-right over keys 1-7.
+    pub fn with_scratch() -> Self {
+        use DrumPad::*;
+        Self::from_pairs(&[
+            (LeftCymbal, 0),
+            (HiHatClose, 1),
+            (HiHatOpen, 1),
+            (LeftPedal, 2),
+            (Snare, 3),
+            (BassDrum, 4),
+            (LeftBassDrum, 4),
+            (HighTom, 5),
+            (LowTom, 6),
+            (FloorTom, 6),
+            (Cymbal, 7),
+            (RideCymbal, 7),
+        ])
+    }
+
+    /// Same layout as [`DtxLaneMap::with_scratch`], with the left cymbal folded into the
+    /// hi-hat key so the chart uses keys only.
+    pub fn keys_only() -> Self {
+        Self::with_scratch().set(DrumPad::LeftCymbal, Some(1))
+    }
+
+    /// Override the col for one pad (`None` turns it into BGM).
+    pub fn set(mut self, pad: DrumPad, col: Option<u8>) -> Self {
+        self.cols[pad as usize] = col;
+        self
+    }
+
+    pub fn col(&self, pad: DrumPad) -> Option<u8> {
+        self.cols[pad as usize]
+    }
+
+    fn from_pairs(pairs: &[(DrumPad, u8)]) -> Self {
+        let mut cols = [None; 12];
+        for &(pad, col) in pairs {
+            cols[pad as usize] = Some(col);
+        }
+        Self { cols }
+    }
+}
+
+impl Default for DtxLaneMap {
+    fn default() -> Self {
+        Self::with_scratch()
+    }
+}
+
+struct Object {
+    measure: u32,
+    channel: u8,
+    /// Position within the measure, `0.0..1.0`.
+    pos: f64,
+    id: String,
+}
+
+/// Import a DTXMania drum chart (`.dtx`) into an `MdfChart`.
+///
+/// Only drum channels (`11`-`1C`) become notes, placed by `lane_map`; guitar/bass
+/// channels and sound effects are ignored. Timing honours `#BPM`, `#BPMxx`, BPM
+/// channels `03`/`08` and measure length (`02`). Objects on channel `01` become BGM
+/// events. `#WAVxx` ids are used as `sound_id`s as-is, and `#DLEVEL` becomes the
+/// version string.
+pub fn import_dtx(src: &str, lane_map: &DtxLaneMap) -> anyhow::Result<MdfChart> {
+    if let Some(col) = lane_map.cols.iter().flatten().find(|&&c| c >= 8) {
+        bail!("lane map col out of range: {col}");
+    }
+
+    let mut header: HashMap<String, String> = HashMap::new();
+    let mut wavs: HashMap<String, String> = HashMap::new();
+    let mut ext_bpms: HashMap<String, f64> = HashMap::new();
+    let mut measure_len: HashMap<u32, f64> = HashMap::new();
+    let mut objects = Vec::new();
+
+    for (line_no, raw) in src.lines().enumerate() {
+        let Some(line) = raw.trim().strip_prefix('#') else {
+            continue;
+        };
+        let line = line.split_once(';').map_or(line, |(l, _)| l);
+        let split = line
+            .find(|c: char| c == ':' || c.is_whitespace())
+            .unwrap_or(line.len());
+        let name = line[..split].to_ascii_uppercase();
+        let value = line[split..].trim_start_matches(':').trim();
+        let err = || format!("invalid line {}: #{line}", line_no + 1);
+
+        // object line: #mmmcc (measure, hex channel)
+        if name.len() == 5
+            && name.is_ascii()
+            && name[1..3].bytes().all(|b| b.is_ascii_digit())
+            && name[3..].bytes().all(|b| b.is_ascii_hexdigit())
+        {
+            let measure = u32::from_str_radix(&name[..1], 36).with_context(err)? * 100
+                + name[1..3].parse::<u32>().with_context(err)?;
+            let channel = u8::from_str_radix(&name[3..], 16).with_context(err)?;
+            if channel == CH_MEASURE_LENGTH {
+                let len: f64 = value.parse().with_context(err)?;
+                if len <= 0.0 {
+                    bail!("measure length must be positive: {}", err());
+                }
+                measure_len.insert(measure, len);
+                continue;
+            }
+
+            let data: Vec<char> = value
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != '_')
+                .collect();
+            if !data.len().is_multiple_of(2) {
+                bail!("object data has odd length: {}", err());
+            }
+            let count = data.len() / 2;
+            for (i, pair) in data.chunks(2).enumerate() {
+                let id: String = pair.iter().collect::<String>().to_ascii_uppercase();
+                if id == "00" {
+                    continue;
+                }
+                objects.push(Object {
+                    measure,
+                    channel,
+                    pos: i as f64 / count as f64,
+                    id,
+                });
+            }
+        } else if let Some(id) = name.strip_prefix("WAV").filter(|id| id.len() == 2) {
+            wavs.insert(id.to_string(), value.to_string());
+        } else if let Some(id) = name.strip_prefix("BPM").filter(|id| id.len() == 2) {
+            ext_bpms.insert(id.to_string(), value.parse().with_context(err)?);
+        } else {
+            header.insert(name, value.to_string());
+        }
+    }
+
+    let last_measure = objects.iter().map(|o| o.measure).max().unwrap_or(0);
+    let mut measure_start = Vec::with_capacity(last_measure as usize + 2);
+    let mut beat = 0.0;
+    for m in 0..=last_measure + 1 {
+        measure_start.push(beat);
+        beat += 4.0 * measure_len.get(&m).copied().unwrap_or(1.0);
+    }
+    let beat_of = |o: &Object| {
+        let m = o.measure as usize;
+        measure_start[m] + (measure_start[m + 1] - measure_start[m]) * o.pos
+    };
+
+    let initial_bpm: f64 = match header.get("BPM") {
+        Some(v) => v.parse().context("invalid #BPM")?,
+        None => 120.0,
+    };
+    let mut bpms = vec![(0.0, initial_bpm)];
+    for o in &objects {
+        let bpm = match o.channel {
+            CH_BPM => u32::from_str_radix(&o.id, 16)
+                .with_context(|| format!("invalid BPM object {} (measure={})", o.id, o.measure))?
+                as f64,
+            CH_BPM_EXT => *ext_bpms
+                .get(&o.id)
+                .with_context(|| format!("undefined #BPM{} (measure={})", o.id, o.measure))?,
+            _ => continue,
+        };
+        bpms.push((beat_of(o), bpm));
+    }
+    if bpms.iter().any(|&(_, bpm)| bpm <= 0.0) {
+        bail!("BPM must be positive");
+    }
+    bpms.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let timing = Timing {
+        bpms,
+        stops: vec![],
+        offset_sec: 0.0,
+    };
+
+    let mut notes = Vec::new();
+    let mut bgm_events = Vec::new();
+    for o in &objects {
+        let sound_id = wavs.contains_key(&o.id).then(|| o.id.clone());
+        let pad_col = DrumPad::from_channel(o.channel).map(|pad| lane_map.col(pad));
+        match (o.channel, pad_col) {
+            (_, Some(Some(col))) => notes.push(Note {
+                time_us: timing.beat_to_us(beat_of(o))?,
+                col,
+                kind: NoteKind::Tap,
+                sound_id,
+            }),
+            (CH_BGM, _) | (_, Some(None)) => {
+                if let Some(sound_id) = sound_id {
+                    bgm_events.push(BgmEvent {
+                        time_us: timing.beat_to_us(beat_of(o))?,
+                        sound_id,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // one note per (time, col); later objects keep playing as BGM
+    notes.sort_by_key(|n| (n.time_us, n.col));
+    let mut kept: Vec<Note> = Vec::with_capacity(notes.len());
+    for n in notes {
+        match kept.last() {
+            Some(prev) if prev.time_us == n.time_us && prev.col == n.col => {
+                if let Some(sound_id) = n.sound_id {
+                    bgm_events.push(BgmEvent {
+                        time_us: n.time_us,
+                        sound_id,
+                    });
+                }
+            }
+            _ => kept.push(n),
+        }
+    }
+
+    let visual_events = timing
+        .bpms
+        .iter()
+        .map(|&(beat, bpm)| {
+            Ok(VisualEvent {
+                time_us: timing.beat_to_us(beat)?,
+                bpm,
+                is_measure_line: false,
+                beat_n: 0,
+                beat_d: 0,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let text = |k: &str| header.get(k).cloned().unwrap_or_default();
+    let mut chart = MdfChart {
+        meta: Metadata {
+            title: text("TITLE"),
+            artist: text("ARTIST"),
+            version: text("DLEVEL"),
+            total_duration_us: 0,
+            tags: vec![],
+        },
+        resources: wavs,
+        resource_slices: HashMap::new(),
+        visual_events,
+        speed_events: vec![],
+        notes: kept,
+        bgm_events,
+        analysis: None,
+    };
+    finalize(&mut chart);
+    Ok(chart)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdf_schema::Microseconds;
+
+    const DTX: &str = "; test chart
+#TITLE: Drum Song
+#ARTIST: Someone
+#DLEVEL: 45
+#BPM: 120
+#WAV01: hh.wav
+#WAV02: sd.wav
+#WAV03: bd.wav
+#WAV04: bgm.ogg
+#BPM01: 240
+
+#00001: 04
+#00011: 01010101
+#00012: 00020002
+#00013: 03000300
+#00018: 02
+#0001B: 03
+#00102: 0.5
+#00108: 0001
+#00111: 0101
+#00213: 03
+#00303: 3C
+#00311: 0001
+#00321: 01 ; guitar, ignored
+";
+
+    fn notes(chart: &MdfChart) -> Vec<(Microseconds, u8)> {
+        chart.notes.iter().map(|n| (n.time_us, n.col)).collect()
+    }
+
+    #[test]
+    fn imports_drums_with_bpm_changes_and_measure_length() {
+        let map = DtxLaneMap::with_scratch().set(DrumPad::LeftPedal, None);
+        let chart = import_dtx(DTX, &map).unwrap();
+        assert_eq!(chart.meta.title, "Drum Song");
+        assert_eq!(chart.meta.artist, "Someone");
+        assert_eq!(chart.meta.version, "45");
+        assert_eq!(
+            chart.resources.get("04").map(String::as_str),
+            Some("bgm.ogg")
+        );
+
+        assert_eq!(
+            notes(&chart),
+            vec![
+                (0, 1),
+                (0, 4),
+                (500_000, 1),
+                (500_000, 3),
+                (1_000_000, 1),
+                (1_000_000, 4),
+                (1_500_000, 1),
+                (1_500_000, 3),
+                // measure 1 is half length; 240 BPM from its second beat
+                (2_000_000, 1),
+                (2_500_000, 1),
+                (2_750_000, 4),
+                // 60 BPM (hex 3C) from measure 3
+                (5_750_000, 1),
+            ]
+        );
+        assert_eq!(chart.notes[3].sound_id.as_deref(), Some("02"));
+        assert_eq!(chart.meta.total_duration_us, 5_750_000);
+
+        let bpm: Vec<(Microseconds, f64)> = chart
+            .visual_events
+            .iter()
+            .map(|e| (e.time_us, e.bpm))
+            .collect();
+        assert_eq!(bpm, vec![(0, 120.0), (2_500_000, 240.0), (3_750_000, 60.0)]);
+    }
+
+    #[test]
+    fn collisions_and_unmapped_pads_become_bgm() {
+        let map = DtxLaneMap::with_scratch().set(DrumPad::LeftPedal, None);
+        let chart = import_dtx(DTX, &map).unwrap();
+        // BGM channel, the open hi-hat colliding with the closed one, and the left pedal
+        let mut bgm: Vec<&str> = chart
+            .bgm_events
+            .iter()
+            .map(|e| e.sound_id.as_str())
+            .collect();
+        bgm.sort();
+        assert_eq!(bgm, vec!["02", "03", "04"]);
+        assert!(chart.bgm_events.iter().all(|e| e.time_us == 0));
+    }
+
+    #[test]
+    fn profiles_place_left_cymbal() {
+        let src = "#BPM 120\n#0001A: 01\n";
+        let scratch = import_dtx(src, &DtxLaneMap::default()).unwrap();
+        assert_eq!(notes(&scratch), vec![(0, 0)]);
+        let keys = import_dtx(src, &DtxLaneMap::keys_only()).unwrap();
+        assert_eq!(notes(&keys), vec![(0, 1)]);
+
+        let err =
+            import_dtx(src, &DtxLaneMap::keys_only().set(DrumPad::Cymbal, Some(8))).unwrap_err();
+        assert!(err.to_string().contains("lane map col out of range: 8"));
+    }
+
+    #[test]
+    fn multibyte_header_names_are_not_object_lines() {
+        // "曲AB" is 5 bytes, like an object line's #mmmcc
+        let chart = import_dtx("#曲AB: x\n#BPM 120\n#00011: 01\n", &DtxLaneMap::default()).unwrap();
+        assert_eq!(notes(&chart), vec![(0, 1)]);
+    }
+}
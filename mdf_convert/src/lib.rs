@@ -0,0 +1,102 @@
+//! Converters between `MdfChart` and other rhythm game chart formats.
+//!
+//! Importers map foreign note types onto the nearest MDFS kinds. Lanes map onto the
+//! `S1234567` layout: charts with up to 7 keys use cols `1..=k`, 8-key charts put their
+//! first column on the scratch lane (col 0).
+
+use anyhow::bail;
+use mdf_schema::{MdfChart, Microseconds, Note, NoteKind};
+
+mod bms;
+mod dtx;
+mod osu;
+mod stepmania;
+
+pub use bms::export_bms;
+pub use dtx::{import_dtx, DrumPad, DtxLaneMap};
+pub use osu::import_osu_mania;
+pub use stepmania::import_stepmania;
+
+/// Resource id used for the song's main audio track in imported charts.
+pub const BGM_SOUND_ID: &str = "BGM";
+
+/// Map a foreign key count onto MDFS cols.
+fn key_lanes(key_count: usize) -> anyhow::Result<Vec<u8>> {
+    match key_count {
+        1..=7 => Ok((1..=key_count as u8).collect()),
+        8 => Ok((0..8).collect()),
+        _ => bail!("unsupported key count: {key_count} (supported: 1-8)"),
+    }
+}
+
+/// Nearest MDFS hold kind for a hold on `col`. `hell` is used for roll-like holds.
+fn hold_kind(col: u8, hell: bool, end_time_us: Microseconds) -> NoteKind {
+    match (col == 0, hell) {
+        (false, false) => NoteKind::ChargeNote { end_time_us },
+        (false, true) => NoteKind::HellChargeNote { end_time_us },
+        (true, false) => NoteKind::BackSpinScratch { end_time_us },
+        (true, true) => NoteKind::HellBackSpinScratch { end_time_us },
+    }
+}
+
+/// Beat → time map shared by the beat-based importers.
+struct Timing {
+    /// (beat, bpm), sorted by beat. The first BPM also applies before its beat.
+    bpms: Vec<(f64, f64)>,
+    /// (beat, seconds), sorted by beat.
+    stops: Vec<(f64, f64)>,
+    offset_sec: f64,
+}
+
+impl Timing {
+    fn beat_to_us(&self, beat: f64) -> anyhow::Result<Microseconds> {
+        let mut sec = 0.0;
+        for (i, &(seg_beat, bpm)) in self.bpms.iter().enumerate() {
+            let seg_start = if i == 0 { 0.0 } else { seg_beat };
+            let seg_end = self.bpms.get(i + 1).map_or(f64::INFINITY, |n| n.0);
+            if beat <= seg_start {
+                break;
+            }
+            sec += (beat.min(seg_end) - seg_start) * 60.0 / bpm;
+        }
+        // a stop on the note's own beat happens after the note is hit
+        sec += self
+            .stops
+            .iter()
+            .filter(|&&(b, _)| b < beat)
+            .map(|&(_, s)| s)
+            .sum::<f64>();
+
+        let t = sec - self.offset_sec;
+        if t < 0.0 {
+            bail!("note at beat {beat} falls before the start of the music");
+        }
+        Ok((t * 1_000_000.0).round() as Microseconds)
+    }
+}
+
+/// Sort events and derive `meta.total_duration_us`, matching the compiler's output rules.
+fn finalize(chart: &mut MdfChart) {
+    chart.notes.sort_by_key(|n| (n.time_us, n.col));
+    chart.bgm_events.sort_by_key(|e| e.time_us);
+    chart.visual_events.sort_by_key(|e| e.time_us);
+
+    let notes_end = chart.notes.iter().map(note_end_us).max().unwrap_or(0);
+    let bgm_end = chart
+        .bgm_events
+        .iter()
+        .map(|e| e.time_us)
+        .max()
+        .unwrap_or(0);
+    let visual_end = chart
+        .visual_events
+        .iter()
+        .map(|e| e.time_us)
+        .max()
+        .unwrap_or(0);
+    chart.meta.total_duration_us = notes_end.max(bgm_end).max(visual_end);
+}
+
+fn note_end_us(n: &Note) -> Microseconds {
+    n.kind.end_time_us().unwrap_or(n.time_us).max(n.time_us)
+}
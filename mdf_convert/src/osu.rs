@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use mdf_schema::{BgmEvent, MdfChart, Metadata, Microseconds, Note, NoteKind, VisualEvent};
+
+use crate::{finalize, hold_kind, key_lanes, BGM_SOUND_ID};
+
+/// osu! playfield width; mania columns are `floor(x * keys / 512)`.
+const PLAYFIELD_WIDTH: f64 = 512.0;
+/// HitObject type bit for mania hold notes.
+const TYPE_HOLD: u32 = 128;
+
+/// Import an osu!mania beatmap (`.osu`, `Mode: 3`) into an `MdfChart`.
+///
+/// - Hold notes become CN (or BSS on the scratch lane of 8K maps).
+/// - Per-object hit sample filenames become keysounds; the filename doubles as the
+///   `sound_id`.
+/// - `AudioFilename` becomes a `BGM` resource played at time 0.
+/// - Uninherited timing points become `visual_events` (BPM only, no guide lines).
+pub fn import_osu_mania(src: &str) -> anyhow::Result<MdfChart> {
+    let sections = parse_sections(src);
+    let kv = |section: &str, key: &str| -> Option<&str> {
+        sections
+            .get(section)?
+            .iter()
+            .find_map(|l| l.split_once(':').filter(|(k, _)| k.trim() == key))
+            .map(|(_, v)| v.trim())
+    };
+
+    let mode = kv("General", "Mode").unwrap_or("0");
+    if mode != "3" {
+        bail!("not an osu!mania beatmap (Mode: {mode})");
+    }
+
+    let key_count: usize = kv("Difficulty", "CircleSize")
+        .context("missing [Difficulty] CircleSize")?
+        .parse::<f64>()
+        .context("invalid CircleSize")? as usize;
+    let lanes = key_lanes(key_count)?;
+
+    let mut resources = HashMap::new();
+    let mut bgm_events = Vec::new();
+    if let Some(audio) = kv("General", "AudioFilename").filter(|a| !a.is_empty()) {
+        resources.insert(BGM_SOUND_ID.to_string(), audio.to_string());
+        bgm_events.push(BgmEvent {
+            time_us: 0,
+            sound_id: BGM_SOUND_ID.to_string(),
+        });
+    }
+
+    let mut visual_events = Vec::new();
+    for line in sections.get("TimingPoints").into_iter().flatten() {
+        let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (Some(time), Some(beat_length)) = (parts.first(), parts.get(1)) else {
+            continue;
+        };
+        let uninherited = parts.get(6).is_none_or(|v| *v == "1");
+        let beat_length: f64 = beat_length
+            .parse()
+            .with_context(|| format!("invalid timing point: {line}"))?;
+        if !uninherited || beat_length <= 0.0 {
+            continue;
+        }
+        let time_ms: f64 = time
+            .parse()
+            .with_context(|| format!("invalid timing point: {line}"))?;
+        visual_events.push(VisualEvent {
+            time_us: (time_ms.max(0.0) * 1000.0).round() as Microseconds,
+            bpm: 60_000.0 / beat_length,
+            is_measure_line: false,
+            beat_n: 0,
+            beat_d: 0,
+        });
+    }
+
+    let mut notes = Vec::new();
+    for line in sections.get("HitObjects").into_iter().flatten() {
+        let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+        if parts.len() < 5 {
+            bail!("invalid hit object: {line}");
+        }
+        let x: f64 = parts[0]
+            .parse()
+            .with_context(|| format!("invalid hit object x: {line}"))?;
+        let time_us = ms_to_us(parts[2], line)?;
+        let ty: u32 = parts[3]
+            .parse()
+            .with_context(|| format!("invalid hit object type: {line}"))?;
+
+        let column = ((x * key_count as f64 / PLAYFIELD_WIDTH).floor() as usize).min(key_count - 1);
+        let col = lanes[column];
+
+        let params: Vec<&str> = parts
+            .get(5)
+            .map(|p| p.split(':').collect())
+            .unwrap_or_default();
+        let (kind, sample_idx) = if ty & TYPE_HOLD != 0 {
+            let end = params
+                .first()
+                .with_context(|| format!("hold without end time: {line}"))?;
+            let end_time_us = ms_to_us(end, line)?;
+            if end_time_us <= time_us {
+                bail!("hold ends before it starts: {line}");
+            }
+            (hold_kind(col, false, end_time_us), 5)
+        } else {
+            (NoteKind::Tap, 4)
+        };
+
+        let sound_id = params
+            .get(sample_idx)
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+            .map(|f| {
+                resources.insert(f.to_string(), f.to_string());
+                f.to_string()
+            });
+
+        notes.push(Note {
+            time_us,
+            col,
+            kind,
+            sound_id,
+        });
+    }
+
+    let mut chart = MdfChart {
+        meta: Metadata {
+            title: kv("Metadata", "Title").unwrap_or_default().to_string(),
+            artist: kv("Metadata", "Artist").unwrap_or_default().to_string(),
+            version: kv("Metadata", "Version").unwrap_or_default().to_string(),
+            total_duration_us: 0,
+            tags: kv("Metadata", "Tags")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        },
+        resources,
+        resource_slices: HashMap::new(),
+        visual_events,
+        speed_events: vec![],
+        notes,
+        bgm_events,
+        analysis: None,
+    };
+    finalize(&mut chart);
+    Ok(chart)
+}
+
+fn parse_sections(src: &str) -> HashMap<&str, Vec<&str>> {
+    let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut current = "";
+    for raw in src.lines() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = name;
+            continue;
+        }
+        sections.entry(current).or_default().push(line);
+    }
+    sections
+}
+
+fn ms_to_us(s: &str, line: &str) -> anyhow::Result<Microseconds> {
+    let ms: f64 = s
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid time: {line}"))?;
+    if ms < 0.0 {
+        bail!("negative time is not supported: {line}");
+    }
+    Ok((ms * 1000.0).round() as Microseconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_7K: &str = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+Mode: 3
+
+[Metadata]
+Title:Song
+Artist:Someone
+Version:7K Hard
+Tags:mania test
+
+[Difficulty]
+CircleSize:7
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+1000,-100,4,2,0,100,0,0
+
+[HitObjects]
+36,192,1000,1,0,0:0:0:0:kick.wav
+475,192,1500,128,0,2000:0:0:0:0:
+";
+
+    #[test]
+    fn imports_7k_taps_holds_and_metadata() {
+        let chart = import_osu_mania(MAP_7K).unwrap();
+        assert_eq!(chart.meta.title, "Song");
+        assert_eq!(chart.meta.artist, "Someone");
+        assert_eq!(chart.meta.version, "7K Hard");
+        assert_eq!(chart.meta.tags, vec!["mania", "test"]);
+        assert_eq!(chart.meta.total_duration_us, 2_000_000);
+
+        assert_eq!(chart.notes.len(), 2);
+        assert_eq!(chart.notes[0].time_us, 1_000_000);
+        assert_eq!(chart.notes[0].col, 1);
+        assert_eq!(chart.notes[0].kind, NoteKind::Tap);
+        assert_eq!(chart.notes[0].sound_id.as_deref(), Some("kick.wav"));
+
+        assert_eq!(chart.notes[1].time_us, 1_500_000);
+        assert_eq!(chart.notes[1].col, 7);
+        assert_eq!(
+            chart.notes[1].kind,
+            NoteKind::ChargeNote {
+                end_time_us: 2_000_000
+            }
+        );
+        assert_eq!(chart.notes[1].sound_id, None);
+
+        assert_eq!(
+            chart.resources.get(BGM_SOUND_ID).map(String::as_str),
+            Some("audio.mp3")
+        );
+        assert_eq!(
+            chart.resources.get("kick.wav").map(String::as_str),
+            Some("kick.wav")
+        );
+        assert_eq!(chart.bgm_events.len(), 1);
+
+        // inherited (green line) timing points are not BPM changes
+        assert_eq!(chart.visual_events.len(), 1);
+        assert_eq!(chart.visual_events[0].bpm, 120.0);
+    }
+
+    #[test]
+    fn eight_key_first_column_is_scratch() {
+        let src = "[General]\nMode: 3\n[Difficulty]\nCircleSize:8\n[HitObjects]\n0,192,0,128,0,500:0:0:0:0:\n511,192,0,1,0,0:0:0:0:\n";
+        let chart = import_osu_mania(src).unwrap();
+        assert_eq!(chart.notes[0].col, 0);
+        assert_eq!(
+            chart.notes[0].kind,
+            NoteKind::BackSpinScratch {
+                end_time_us: 500_000
+            }
+        );
+        assert_eq!(chart.notes[1].col, 7);
+    }
+
+    #[test]
+    fn rejects_non_mania_and_unsupported_key_counts() {
+        let err = import_osu_mania("[General]\nMode: 0\n").unwrap_err();
+        assert!(err.to_string().contains("not an osu!mania beatmap"));
+
+        let err =
+            import_osu_mania("[General]\nMode: 3\n[Difficulty]\nCircleSize:10\n").unwrap_err();
+        assert!(err.to_string().contains("unsupported key count: 10"));
+    }
+}
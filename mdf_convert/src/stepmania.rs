@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context};
+use mdf_schema::{BgmEvent, MdfChart, Metadata, Microseconds, Note, NoteKind, VisualEvent};
+
+use crate::{finalize, hold_kind, key_lanes, Timing, BGM_SOUND_ID};
+
+#[derive(Debug, Default)]
+struct SmChart<'a> {
+    steps_type: &'a str,
+    difficulty: &'a str,
+    notes: &'a str,
+    // .ssc split timing (falls back to the song-level values when absent)
+    bpms: Option<&'a str>,
+    stops: Option<&'a str>,
+    offset: Option<&'a str>,
+}
+
+/// Import a StepMania chart (`.sm` or `.ssc`) into an `MdfChart`.
+///
+/// `difficulty` selects a chart by its difficulty name (case-insensitive, e.g. `"Hard"`);
+/// `None` takes the first chart. Timing honours `#OFFSET`, `#BPMS` and `#STOPS`
+/// (delays/warps are not supported).
+///
+/// - Holds (`2..3`) become CN, rolls (`4..3`) become HCN (BSS/HBSS on the scratch lane).
+/// - Lifts (`L`) become taps; mines (`M`), fakes (`F`) and keysound-only cells (`K`) are
+///   dropped.
+/// - `#MUSIC` becomes a `BGM` resource played at time 0.
+pub fn import_stepmania(src: &str, difficulty: Option<&str>) -> anyhow::Result<MdfChart> {
+    let mut song: HashMap<String, &str> = HashMap::new();
+    let mut charts: Vec<SmChart> = Vec::new();
+    let mut in_ssc_chart = false;
+
+    for (name, value) in parse_tags(src) {
+        match (name.as_str(), in_ssc_chart) {
+            ("NOTEDATA", _) => {
+                in_ssc_chart = true;
+                charts.push(SmChart::default());
+            }
+            ("NOTES", false) => charts.push(parse_sm_notes(value)?),
+            (_, false) => {
+                song.insert(name, value);
+            }
+            (_, true) => {
+                let chart = charts.last_mut().expect("NOTEDATA pushes a chart");
+                match name.as_str() {
+                    "STEPSTYPE" => chart.steps_type = value.trim(),
+                    "DIFFICULTY" => chart.difficulty = value.trim(),
+                    "NOTES" => chart.notes = value,
+                    "BPMS" => chart.bpms = Some(value),
+                    "STOPS" => chart.stops = Some(value),
+                    "OFFSET" => chart.offset = Some(value),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let chart = match difficulty {
+        Some(d) => charts
+            .iter()
+            .find(|c| c.difficulty.eq_ignore_ascii_case(d))
+            .with_context(|| format!("no chart with difficulty {d}"))?,
+        None => charts.first().context("no charts in file")?,
+    };
+
+    let key_count = match chart.steps_type {
+        "dance-single" => 4,
+        "pump-single" => 5,
+        "dance-solo" | "pump-halfdouble" => 6,
+        "kb7-single" => 7,
+        "dance-double" => 8,
+        other => bail!("unsupported steps type: {other}"),
+    };
+    let lanes = key_lanes(key_count)?;
+
+    let timing = Timing {
+        bpms: parse_beat_pairs(
+            chart.bpms.or(song.get("BPMS").copied()).unwrap_or(""),
+            "BPMS",
+        )?,
+        stops: parse_beat_pairs(
+            chart.stops.or(song.get("STOPS").copied()).unwrap_or(""),
+            "STOPS",
+        )?,
+        offset_sec: chart
+            .offset
+            .or(song.get("OFFSET").copied())
+            .map(|v| v.trim().parse::<f64>().context("invalid #OFFSET"))
+            .transpose()?
+            .unwrap_or(0.0),
+    };
+    if timing.bpms.is_empty() || timing.bpms.iter().any(|&(_, bpm)| bpm <= 0.0) {
+        bail!("#BPMS must list at least one positive BPM");
+    }
+
+    let notes = parse_note_data(chart.notes, key_count, &lanes, &timing)?;
+
+    let visual_events = timing
+        .bpms
+        .iter()
+        .map(|&(beat, bpm)| VisualEvent {
+            // a BPM change before the music starts takes effect at time 0
+            time_us: timing.beat_to_us(beat).unwrap_or(0),
+            bpm,
+            is_measure_line: false,
+            beat_n: 0,
+            beat_d: 0,
+        })
+        .collect();
+
+    let mut resources = HashMap::new();
+    let mut bgm_events = Vec::new();
+    if let Some(music) = song
+        .get("MUSIC")
+        .map(|m| m.trim())
+        .filter(|m| !m.is_empty())
+    {
+        resources.insert(BGM_SOUND_ID.to_string(), music.to_string());
+        bgm_events.push(BgmEvent {
+            time_us: 0,
+            sound_id: BGM_SOUND_ID.to_string(),
+        });
+    }
+
+    let text = |k: &str| {
+        song.get(k)
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut out = MdfChart {
+        meta: Metadata {
+            title: text("TITLE"),
+            artist: text("ARTIST"),
+            version: chart.difficulty.to_string(),
+            total_duration_us: 0,
+            tags: vec![],
+        },
+        resources,
+        resource_slices: HashMap::new(),
+        visual_events,
+        speed_events: vec![],
+        notes,
+        bgm_events,
+        analysis: None,
+    };
+    finalize(&mut out);
+    Ok(out)
+}
+
+/// Split `#NAME:VALUE;` tags, dropping `//` comments. Names are upper-cased.
+fn parse_tags(src: &str) -> Vec<(String, &str)> {
+    let mut tags = Vec::new();
+    let mut rest = src;
+    while let Some(start) = rest.find('#') {
+        let after = &rest[start + 1..];
+        let Some(colon) = after.find(':') else { break };
+        let end = after[colon..].find(';').map_or(after.len(), |i| colon + i);
+        tags.push((
+            after[..colon].trim().to_ascii_uppercase(),
+            &after[colon + 1..end],
+        ));
+        rest = &after[end..];
+    }
+    tags
+}
+
+fn strip_comments(s: &str) -> String {
+    s.lines()
+        .map(|l| l.find("//").map_or(l, |i| &l[..i]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `.sm` `#NOTES:type:description:difficulty:meter:radar:data;`
+fn parse_sm_notes(value: &str) -> anyhow::Result<SmChart<'_>> {
+    let fields: Vec<&str> = value.splitn(6, ':').collect();
+    if fields.len() != 6 {
+        bail!("invalid #NOTES (expected 6 fields, got {})", fields.len());
+    }
+    Ok(SmChart {
+        steps_type: fields[0].trim(),
+        difficulty: fields[2].trim(),
+        notes: fields[5],
+        ..SmChart::default()
+    })
+}
+
+fn parse_beat_pairs(s: &str, tag: &str) -> anyhow::Result<Vec<(f64, f64)>> {
+    let s = strip_comments(s);
+    let mut out = Vec::new();
+    for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (beat, value) = part
+            .split_once('=')
+            .with_context(|| format!("invalid #{tag} entry: {part}"))?;
+        let beat: f64 = beat
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid #{tag} entry: {part}"))?;
+        let value: f64 = value
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid #{tag} entry: {part}"))?;
+        out.push((beat, value));
+    }
+    out.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(out)
+}
+
+fn parse_note_data(
+    data: &str,
+    key_count: usize,
+    lanes: &[u8],
+    timing: &Timing,
+) -> anyhow::Result<Vec<Note>> {
+    let data = strip_comments(data);
+    if data.contains('&') {
+        bail!("multi-player (couple/routine) note data is not supported");
+    }
+
+    let mut notes = Vec::new();
+    let mut open: Vec<Option<(Microseconds, bool)>> = vec![None; key_count];
+
+    for (measure_idx, measure) in data.split(',').enumerate() {
+        let rows: Vec<&str> = measure
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .collect();
+        for (row_idx, row) in rows.iter().enumerate() {
+            if row.chars().count() != key_count {
+                bail!(
+                    "row has {} columns, expected {key_count} (measure={measure_idx}, row={row})",
+                    row.chars().count()
+                );
+            }
+            let beat = (measure_idx as f64 + row_idx as f64 / rows.len() as f64) * 4.0;
+            let time = || timing.beat_to_us(beat);
+
+            for (column, ch) in row.chars().enumerate() {
+                let col = lanes[column];
+                match ch {
+                    '0' | 'M' | 'F' | 'K' => {}
+                    '1' | 'L' => notes.push(Note {
+                        time_us: time()?,
+                        col,
+                        kind: NoteKind::Tap,
+                        sound_id: None,
+                    }),
+                    '2' | '4' => {
+                        if open[column].is_some() {
+                            bail!("hold started while another is open (measure={measure_idx}, column={column})");
+                        }
+                        open[column] = Some((time()?, ch == '4'));
+                    }
+                    '3' => {
+                        let Some((start_us, is_roll)) = open[column].take() else {
+                            bail!(
+                                "hold tail without head (measure={measure_idx}, column={column})"
+                            );
+                        };
+                        notes.push(Note {
+                            time_us: start_us,
+                            col,
+                            kind: hold_kind(col, is_roll, time()?),
+                            sound_id: None,
+                        });
+                    }
+                    other => {
+                        bail!("unsupported note char '{other}' (measure={measure_idx}, row={row})")
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(column) = open.iter().position(Option::is_some) {
+        bail!("unclosed hold (column={column})");
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SM: &str = "#TITLE:Song;
+#ARTIST:Someone;
+#MUSIC:song.ogg;
+#OFFSET:0.000;
+#BPMS:0.000=120.000,8.000=240.000;
+#STOPS:4.000=0.500;
+#NOTES:
+     dance-single:
+     :
+     Easy:
+     1:
+     0,0,0,0,0:
+1000
+0000
+0200
+0000
+,
+0010 // beat 4 (stop applies after this row)
+0000
+0304
+0000
+,
+0000
+0000
+0000
+0003
+;
+#NOTES:
+     dance-single:
+     :
+     Hard:
+     9:
+     0,0,0,0,0:
+0001
+0000
+0000
+0000
+;
+";
+
+    #[test]
+    fn imports_sm_holds_rolls_bpm_changes_and_stops() {
+        let chart = import_stepmania(SM, None).unwrap();
+        assert_eq!(chart.meta.title, "Song");
+        assert_eq!(chart.meta.artist, "Someone");
+        assert_eq!(chart.meta.version, "Easy");
+        assert_eq!(
+            chart.resources.get(BGM_SOUND_ID).map(String::as_str),
+            Some("song.ogg")
+        );
+
+        // 120 BPM: one beat = 500ms
+        let n: Vec<(Microseconds, u8, &NoteKind)> = chart
+            .notes
+            .iter()
+            .map(|n| (n.time_us, n.col, &n.kind))
+            .collect();
+        assert_eq!(
+            n,
+            vec![
+                (0, 1, &NoteKind::Tap),
+                (
+                    1_000_000,
+                    2,
+                    &NoteKind::ChargeNote {
+                        end_time_us: 3_500_000
+                    }
+                ),
+                (2_000_000, 3, &NoteKind::Tap),
+                // beat 6 = 3.0s + 0.5s stop at beat 4
+                (
+                    3_500_000,
+                    4,
+                    &NoteKind::HellChargeNote {
+                        end_time_us: 5_250_000
+                    }
+                ),
+            ]
+        );
+        // beat 8 switches to 240 BPM: beats 8..11 take 0.75s on top of 4.5s
+        assert_eq!(chart.meta.total_duration_us, 5_250_000);
+        assert_eq!(chart.visual_events.len(), 2);
+        assert_eq!(chart.visual_events[1].time_us, 4_500_000);
+        assert_eq!(chart.visual_events[1].bpm, 240.0);
+    }
+
+    #[test]
+    fn selects_difficulty_case_insensitively() {
+        let chart = import_stepmania(SM, Some("hard")).unwrap();
+        assert_eq!(chart.meta.version, "Hard");
+        assert_eq!(chart.notes.len(), 1);
+        assert_eq!(chart.notes[0].col, 4);
+
+        let err = import_stepmania(SM, Some("Challenge")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("no chart with difficulty Challenge"));
+    }
+
+    #[test]
+    fn imports_ssc_with_split_timing() {
+        let src = "#VERSION:0.83;
+#TITLE:Song;
+#BPMS:0=60;
+#NOTEDATA:;
+#STEPSTYPE:kb7-single;
+#DIFFICULTY:Hard;
+#BPMS:0=120;
+#OFFSET:-0.5;
+#NOTES:
+0000001
+0000000
+;
+";
+        let chart = import_stepmania(src, None).unwrap();
+        assert_eq!(chart.meta.version, "Hard");
+        assert_eq!(chart.notes.len(), 1);
+        assert_eq!(chart.notes[0].col, 7);
+        assert_eq!(chart.notes[0].time_us, 500_000);
+    }
+
+    #[test]
+    fn rejects_unclosed_holds_and_unsupported_types() {
+        let src = "#BPMS:0=120;\n#NOTES:dance-single::Easy:1::\n2000\n;";
+        let err = import_stepmania(src, None).unwrap_err();
+        assert!(err.to_string().contains("unclosed hold"));
+
+        let src = "#BPMS:0=120;\n#NOTES:lights-cabinet::Easy:1::\n00000000\n;";
+        let err = import_stepmania(src, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported steps type: lights-cabinet"));
+    }
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use mdf_schema::{MdfChart, SoundSlice};
+
+/// Bytes read from the start (and, for Ogg, the end) of a file to identify it.
+const PROBE_LEN: u64 = 64 * 1024;
+
+/// Audio container, as named by a file's extension or detected from its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Ogg,
+    Flac,
+    Mp3,
+}
+
+impl AudioFormat {
+    fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "wav" => Some(Self::Wav),
+            "ogg" | "oga" | "opus" => Some(Self::Ogg),
+            "flac" => Some(Self::Flac),
+            "mp3" => Some(Self::Mp3),
+            _ => None,
+        }
+    }
+
+    fn sniff(head: &[u8]) -> Option<Self> {
+        match head {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some(Self::Wav),
+            [b'O', b'g', b'g', b'S', ..] => Some(Self::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Some(Self::Flac),
+            [b'I', b'D', b'3', ..] => Some(Self::Mp3),
+            // MPEG audio frame sync
+            [0xFF, b, ..] if b & 0xE0 == 0xE0 => Some(Self::Mp3),
+            _ => None,
+        }
+    }
+}
+
+/// Why a referenced sound could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingReason {
+    /// The sound_id is referenced by a note/BGM event but has no `resources` entry.
+    NotInManifest,
+    /// The resolved path does not exist.
+    NotFound,
+    /// The resolved path exists but is not a regular file.
+    NotAFile,
+    /// The file exists but could not be opened (message from the OS).
+    Unreadable(String),
+    /// The file is empty, so it cannot contain decodable audio.
+    Empty,
+    /// The file does not start with a WAV, Ogg, FLAC or MP3 header.
+    NotAudio,
+    /// The file's content is in a different format than its extension says.
+    FormatMismatch {
+        extension: AudioFormat,
+        content: AudioFormat,
+    },
+    /// The header declares more data than the file holds (checked for WAV).
+    Truncated,
+    /// The `resource_slices` entry for this sound_id ends past the end of the audio.
+    SliceOutOfRange {
+        start_ms: u64,
+        len_ms: u64,
+        duration_ms: u64,
+    },
+}
+
+/// A sound_id whose file cannot be loaded, with the chart events that use it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingResource {
+    pub sound_id: String,
+    /// Resolved path, or `None` for [`MissingReason::NotInManifest`].
+    pub path: Option<PathBuf>,
+    pub reason: MissingReason,
+    /// Indices into `MdfChart.notes` that reference this sound_id.
+    pub note_indices: Vec<usize>,
+    /// Indices into `MdfChart.bgm_events` that reference this sound_id.
+    pub bgm_event_indices: Vec<usize>,
+}
+
+/// Resolve every `resources` entry against `base_dir` (usually the chart's directory)
+/// and report the ones that cannot be loaded, plus sound_ids referenced without a
+/// `resources` entry.
+///
+/// Each file must exist, be readable and start with a WAV, Ogg, FLAC or MP3 header that
+/// matches its extension (unknown extensions accept any of them); WAV files must be as
+/// long as their header says. Sliced sound_ids must fit inside the audio, which is checked
+/// where the duration can be read from the header (WAV, FLAC, Ogg Vorbis/Opus; not MP3).
+/// Full decoding is left to the player. Unreferenced broken entries are reported too, with
+/// empty index lists. The result is sorted by `sound_id`.
+pub fn audit_resources(chart: &MdfChart, base_dir: impl AsRef<Path>) -> Vec<MissingResource> {
+    let base_dir = base_dir.as_ref();
+
+    let mut refs: BTreeMap<&str, (Vec<usize>, Vec<usize>)> = BTreeMap::new();
+    for id in chart.resources.keys() {
+        refs.entry(id.as_str()).or_default();
+    }
+    for (i, n) in chart.notes.iter().enumerate() {
+        if let Some(id) = n.sound_id.as_deref() {
+            refs.entry(id).or_default().0.push(i);
+        }
+    }
+    for (i, e) in chart.bgm_events.iter().enumerate() {
+        refs.entry(e.sound_id.as_str()).or_default().1.push(i);
+    }
+
+    let mut out = Vec::new();
+    for (sound_id, (note_indices, bgm_event_indices)) in refs {
+        let (path, reason) = match chart.resources.get(sound_id) {
+            None => (None, MissingReason::NotInManifest),
+            Some(rel) => {
+                let full = base_dir.join(rel);
+                match check_file(&full, chart.resource_slices.get(sound_id)) {
+                    Some(reason) => (Some(full), reason),
+                    None => continue,
+                }
+            }
+        };
+        out.push(MissingResource {
+            sound_id: sound_id.to_string(),
+            path,
+            reason,
+            note_indices,
+            bgm_event_indices,
+        });
+    }
+    out
+}
+
+fn check_file(path: &Path, slice: Option<&SoundSlice>) -> Option<MissingReason> {
+    let meta = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Some(MissingReason::NotFound),
+        Err(e) => return Some(MissingReason::Unreadable(e.to_string())),
+    };
+    if !meta.is_file() {
+        return Some(MissingReason::NotAFile);
+    }
+    if meta.len() == 0 {
+        return Some(MissingReason::Empty);
+    }
+    match check_audio(path, meta.len(), slice) {
+        Ok(reason) => reason,
+        Err(e) => Some(MissingReason::Unreadable(e.to_string())),
+    }
+}
+
+fn check_audio(
+    path: &Path,
+    len: u64,
+    slice: Option<&SoundSlice>,
+) -> io::Result<Option<MissingReason>> {
+    let mut file = fs::File::open(path)?;
+    let mut head = Vec::new();
+    (&mut file).take(PROBE_LEN).read_to_end(&mut head)?;
+
+    let Some(content) = AudioFormat::sniff(&head) else {
+        return Ok(Some(MissingReason::NotAudio));
+    };
+    if let Some(extension) = AudioFormat::from_extension(path) {
+        if extension != content {
+            return Ok(Some(MissingReason::FormatMismatch { extension, content }));
+        }
+    }
+
+    let duration_ms = match content {
+        AudioFormat::Wav => match wav_info(&head) {
+            Some(info) if info.end > len => return Ok(Some(MissingReason::Truncated)),
+            Some(info) => info.duration_ms,
+            None => None,
+        },
+        AudioFormat::Flac => flac_duration_ms(&head),
+        AudioFormat::Ogg if slice.is_some() => {
+            let mut tail = Vec::new();
+            file.seek(SeekFrom::Start(len.saturating_sub(PROBE_LEN)))?;
+            file.read_to_end(&mut tail)?;
+            ogg_duration_ms(&head, &tail)
+        }
+        AudioFormat::Ogg | AudioFormat::Mp3 => None,
+    };
+
+    if let (Some(slice), Some(duration_ms)) = (slice, duration_ms) {
+        if slice.start_ms.saturating_add(slice.len_ms) > duration_ms {
+            return Ok(Some(MissingReason::SliceOutOfRange {
+                start_ms: slice.start_ms,
+                len_ms: slice.len_ms,
+                duration_ms,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+struct WavInfo {
+    /// Byte offset where the RIFF chunk (or its `data` chunk, if later) ends.
+    end: u64,
+    duration_ms: Option<u64>,
+}
+
+/// Walk the RIFF chunks found in `head` for the declared size and the `data` duration.
+fn wav_info(head: &[u8]) -> Option<WavInfo> {
+    let u32_at = |i: usize| Some(u32::from_le_bytes(head.get(i..i + 4)?.try_into().ok()?));
+    let mut end = u64::from(u32_at(4)?) + 8;
+    let mut byte_rate = None;
+    let mut duration_ms = None;
+
+    let mut pos = 12;
+    while let (Some(id), Some(size)) = (head.get(pos..pos + 4), u32_at(pos + 4)) {
+        let body = pos + 8;
+        match id {
+            b"fmt " => byte_rate = u32_at(body + 8).filter(|&r| r > 0),
+            b"data" => {
+                end = end.max(body as u64 + u64::from(size));
+                duration_ms = byte_rate.map(|r| u64::from(size) * 1000 / u64::from(r));
+                break;
+            }
+            _ => {}
+        }
+        // chunks are padded to an even length
+        pos = body.saturating_add(size as usize + (size as usize & 1));
+    }
+    Some(WavInfo { end, duration_ms })
+}
+
+/// From the STREAMINFO block, which must come first; `None` if the sample count is unknown.
+fn flac_duration_ms(head: &[u8]) -> Option<u64> {
+    let info = head.get(8..8 + 34)?;
+    let sample_rate =
+        u64::from(info[10]) << 12 | u64::from(info[11]) << 4 | u64::from(info[12]) >> 4;
+    let samples = u64::from(info[13] & 0x0F) << 32
+        | u64::from(u32::from_be_bytes(info[14..18].try_into().ok()?));
+    (sample_rate > 0 && samples > 0).then(|| samples * 1000 / sample_rate)
+}
+
+/// Sample rate from the Vorbis/Opus identification header on the first page, and the
+/// granule position of the last page in `tail`.
+fn ogg_duration_ms(head: &[u8], tail: &[u8]) -> Option<u64> {
+    let segments = usize::from(*head.get(26)?);
+    let packet = head.get(27 + segments..)?;
+    let (sample_rate, pre_skip) = if packet.starts_with(b"\x01vorbis") {
+        (
+            u64::from(u32::from_le_bytes(packet.get(12..16)?.try_into().ok()?)),
+            0,
+        )
+    } else if packet.starts_with(b"OpusHead") {
+        // Opus granule positions always count 48 kHz samples
+        (
+            48_000,
+            u64::from(u16::from_le_bytes(packet.get(10..12)?.try_into().ok()?)),
+        )
+    } else {
+        return None;
+    };
+
+    let last_page = tail.windows(4).rposition(|w| w == b"OggS")?;
+    let granule = u64::from_le_bytes(tail.get(last_page + 6..last_page + 14)?.try_into().ok()?);
+    (sample_rate > 0).then(|| granule.saturating_sub(pre_skip) * 1000 / sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdf_schema::{BgmEvent, Note, NoteKind, SoundSlice};
+    use std::{
+        collections::HashMap,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    fn tmp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "oxidizer_mdf_runner_{name}_{}_{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 8 kHz mono 8-bit PCM, i.e. 8 bytes per millisecond.
+    fn wav(duration_ms: u32) -> Vec<u8> {
+        let data_len = duration_ms * 8;
+        let mut w = Vec::new();
+        w.extend_from_slice(b"RIFF");
+        w.extend_from_slice(&(36 + data_len).to_le_bytes());
+        w.extend_from_slice(b"WAVEfmt ");
+        w.extend_from_slice(&16u32.to_le_bytes());
+        w.extend_from_slice(&[1, 0, 1, 0]); // PCM, mono
+        w.extend_from_slice(&8_000u32.to_le_bytes()); // sample rate
+        w.extend_from_slice(&8_000u32.to_le_bytes()); // byte rate
+        w.extend_from_slice(&[1, 0, 8, 0]); // block align, bits per sample
+        w.extend_from_slice(b"data");
+        w.extend_from_slice(&data_len.to_le_bytes());
+        w.resize(w.len() + data_len as usize, 0x80);
+        w
+    }
+
+    fn chart(resources: &[(&str, &str)], note_ids: &[&str], bgm_ids: &[&str]) -> MdfChart {
+        MdfChart {
+            resources: resources
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            notes: note_ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| Note {
+                    time_us: i as u64,
+                    col: 1,
+                    kind: NoteKind::Tap,
+                    sound_id: Some(id.to_string()),
+                })
+                .collect(),
+            bgm_events: bgm_ids
+                .iter()
+                .map(|id| BgmEvent {
+                    time_us: 0,
+                    sound_id: id.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn all_present_reports_nothing() {
+        let dir = tmp_dir("audit_ok");
+        fs::write(dir.join("kick.wav"), wav(10)).unwrap();
+
+        let c = chart(&[("K01", "kick.wav")], &["K01"], &["K01"]);
+        assert!(audit_resources(&c, &dir).is_empty());
+    }
+
+    #[test]
+    fn reports_missing_empty_and_undeclared_with_references() {
+        let dir = tmp_dir("audit_missing");
+        fs::write(dir.join("empty.wav"), b"").unwrap();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+
+        let c = chart(
+            &[("K01", "kick.wav"), ("K02", "empty.wav"), ("K03", "sub")],
+            &["K01", "K02", "K01", "K09"],
+            &["K01"],
+        );
+        let report = audit_resources(&c, &dir);
+        let ids: Vec<&str> = report.iter().map(|m| m.sound_id.as_str()).collect();
+        assert_eq!(ids, vec!["K01", "K02", "K03", "K09"]);
+
+        assert_eq!(report[0].reason, MissingReason::NotFound);
+        assert_eq!(report[0].path.as_deref(), Some(dir.join("kick.wav").as_path()));
+        assert_eq!(report[0].note_indices, vec![0, 2]);
+        assert_eq!(report[0].bgm_event_indices, vec![0]);
+
+        assert_eq!(report[1].reason, MissingReason::Empty);
+        assert_eq!(report[1].note_indices, vec![1]);
+
+        assert_eq!(report[2].reason, MissingReason::NotAFile);
+        assert!(report[2].note_indices.is_empty());
+
+        assert_eq!(report[3].reason, MissingReason::NotInManifest);
+        assert_eq!(report[3].path, None);
+        assert_eq!(report[3].note_indices, vec![3]);
+    }
+
+    #[test]
+    fn reports_files_that_are_not_the_audio_they_claim() {
+        let dir = tmp_dir("audit_content");
+        fs::write(dir.join("garbage.wav"), b"not audio at all").unwrap();
+        fs::write(dir.join("vorbis.wav"), b"OggS\0\x02").unwrap();
+        let mut cut = wav(100);
+        cut.truncate(200);
+        fs::write(dir.join("cut.wav"), cut).unwrap();
+        fs::write(dir.join("song.mp3"), b"ID3\x04\0").unwrap();
+
+        let c = chart(
+            &[
+                ("K01", "garbage.wav"),
+                ("K02", "vorbis.wav"),
+                ("K03", "cut.wav"),
+                ("K04", "song.mp3"),
+            ],
+            &["K01", "K02", "K03", "K04"],
+            &[],
+        );
+        let reasons: Vec<MissingReason> = audit_resources(&c, &dir)
+            .into_iter()
+            .map(|m| m.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                MissingReason::NotAudio,
+                MissingReason::FormatMismatch {
+                    extension: AudioFormat::Wav,
+                    content: AudioFormat::Ogg,
+                },
+                MissingReason::Truncated,
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_slices_past_the_end_of_the_audio() {
+        let dir = tmp_dir("audit_slices");
+        fs::write(dir.join("bgm.wav"), wav(1_000)).unwrap();
+        // STREAMINFO: 44.1 kHz, 88_200 samples (2 s)
+        let mut flac = b"fLaC\x80\0\0\x22".to_vec();
+        flac.extend_from_slice(&[0; 10]);
+        flac.extend_from_slice(&[0x0A, 0xC4, 0x40, 0xF0, 0x00, 0x01, 0x58, 0x88]);
+        flac.extend_from_slice(&[0; 16]);
+        fs::write(dir.join("bgm.flac"), flac).unwrap();
+
+        let slice = |start_ms, len_ms| SoundSlice { start_ms, len_ms };
+        let mut c = chart(
+            &[
+                ("A", "bgm.wav"),
+                ("B", "bgm.wav"),
+                ("C", "bgm.flac"),
+                ("D", "bgm.flac"),
+            ],
+            &["A", "B", "C", "D"],
+            &[],
+        );
+        c.resource_slices = HashMap::from([
+            ("A".to_string(), slice(500, 500)),
+            ("B".to_string(), slice(900, 200)),
+            ("C".to_string(), slice(1_000, 1_000)),
+            ("D".to_string(), slice(1_999, 2)),
+        ]);
+
+        let report = audit_resources(&c, &dir);
+        let reasons: Vec<(&str, &MissingReason)> = report
+            .iter()
+            .map(|m| (m.sound_id.as_str(), &m.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (
+                    "B",
+                    &MissingReason::SliceOutOfRange {
+                        start_ms: 900,
+                        len_ms: 200,
+                        duration_ms: 1_000,
+                    }
+                ),
+                (
+                    "D",
+                    &MissingReason::SliceOutOfRange {
+                        start_ms: 1_999,
+                        len_ms: 2,
+                        duration_ms: 2_000,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ogg_duration_comes_from_the_last_granule_position() {
+        let mut head = b"OggS".to_vec();
+        head.resize(26, 0);
+        head.extend_from_slice(&[1, 19]);
+        head.extend_from_slice(b"OpusHead\x01\x02");
+        head.extend_from_slice(&312u16.to_le_bytes());
+
+        let mut tail = b"...OggS\0\x04".to_vec();
+        tail.extend_from_slice(&(3 * 48_000 + 312u64).to_le_bytes());
+        assert_eq!(ogg_duration_ms(&head, &tail), Some(3_000));
+    }
+}
@@ -0,0 +1,439 @@
+use mdf_schema::{MdfChart, Microseconds, Note, NoteKind};
+use thiserror::Error;
+
+/// Number of lanes in a chart (`S1234567`: lane 0 is scratch, 1-7 are keys).
+const LANE_COUNT: u8 = 8;
+
+/// A structural problem found in a loaded chart before play starts.
+///
+/// `note_index` refers to the position in `MdfChart.notes`; `UnsortedEvents::index` to the
+/// position in the event list named by `list`.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ChartIntegrityError {
+    #[error("notes are not sorted by time_us (note_index={note_index}, time_us={time_us}, prev_time_us={prev_time_us})")]
+    UnsortedNotes {
+        note_index: usize,
+        time_us: Microseconds,
+        prev_time_us: Microseconds,
+    },
+
+    #[error("lane out of range (note_index={note_index}, col={col})")]
+    LaneOutOfRange { note_index: usize, col: u8 },
+
+    #[error("note kind not allowed on this lane (note_index={note_index}, col={col}, kind={kind})")]
+    KindNotAllowedOnLane {
+        note_index: usize,
+        col: u8,
+        kind: &'static str,
+    },
+
+    #[error("hold must end after it starts (note_index={note_index}, time_us={time_us}, end_time_us={end_time_us})")]
+    HoldEndNotAfterStart {
+        note_index: usize,
+        time_us: Microseconds,
+        end_time_us: Microseconds,
+    },
+
+    #[error("reverse checkpoint outside hold span (note_index={note_index}, checkpoint_us={checkpoint_us}, time_us={time_us}, end_time_us={end_time_us})")]
+    CheckpointOutsideHold {
+        note_index: usize,
+        checkpoint_us: Microseconds,
+        time_us: Microseconds,
+        end_time_us: Microseconds,
+    },
+
+    #[error("note overlaps another note on the same lane (note_index={note_index}, col={col}, other_index={other_index})")]
+    LaneOverlap {
+        note_index: usize,
+        col: u8,
+        /// The earlier note: a hold still open when `note_index` starts, or a note
+        /// starting at the same time.
+        other_index: usize,
+    },
+
+    #[error("{list} are not sorted by time_us (index={index}, time_us={time_us}, prev_time_us={prev_time_us})")]
+    UnsortedEvents {
+        list: &'static str,
+        index: usize,
+        time_us: Microseconds,
+        prev_time_us: Microseconds,
+    },
+
+    #[error("reverse checkpoints are not strictly ascending (note_index={note_index}, checkpoint_us={checkpoint_us})")]
+    UnsortedCheckpoints {
+        note_index: usize,
+        checkpoint_us: Microseconds,
+    },
+}
+
+/// Validate a loaded chart before gameplay begins.
+///
+/// Checks that notes and BGM/visual/speed events are sorted by `time_us`, lanes are in
+/// range, scratch-only kinds sit on lane 0 (and CN/HCN do not), holds end strictly after
+/// they start, MSS/HMSS checkpoints are strictly ascending and strictly inside the hold,
+/// and no note starts inside a hold (or at the same time as another note) on its lane.
+/// Returns the first problem found; see `validate_chart_all()` for every problem.
+pub fn validate_chart(chart: &MdfChart) -> Result<(), ChartIntegrityError> {
+    match validate_chart_all(chart).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Like `validate_chart()`, but returns every problem found.
+///
+/// Each note reports at most its first problem, plus `UnsortedNotes` if it is out of
+/// order; each event list reports every out-of-order event.
+pub fn validate_chart_all(chart: &MdfChart) -> Vec<ChartIntegrityError> {
+    let mut errors = Vec::new();
+    let mut prev_time_us: Microseconds = 0;
+    // per lane: (note_index, time_us, end_time_us) of the note that reaches furthest
+    let mut lanes: [Option<(usize, Microseconds, Microseconds)>; LANE_COUNT as usize] =
+        [None; LANE_COUNT as usize];
+
+    for (note_index, note) in chart.notes.iter().enumerate() {
+        if note.time_us < prev_time_us {
+            errors.push(ChartIntegrityError::UnsortedNotes {
+                note_index,
+                time_us: note.time_us,
+                prev_time_us,
+            });
+        }
+        prev_time_us = note.time_us;
+
+        if let Err(e) = validate_note(note_index, note) {
+            errors.push(e);
+            continue;
+        }
+
+        let end_time_us = note.kind.end_time_us().unwrap_or(note.time_us);
+        let lane = &mut lanes[note.col as usize];
+        if let Some((other_index, start_us, end_us)) = *lane {
+            if note.time_us < end_us || note.time_us == start_us {
+                errors.push(ChartIntegrityError::LaneOverlap {
+                    note_index,
+                    col: note.col,
+                    other_index,
+                });
+            }
+        }
+        if lane.is_none_or(|(_, _, end_us)| end_time_us >= end_us) {
+            *lane = Some((note_index, note.time_us, end_time_us));
+        }
+    }
+
+    let bgm = chart.bgm_events.iter().map(|e| e.time_us);
+    let visual = chart.visual_events.iter().map(|e| e.time_us);
+    let speed = chart.speed_events.iter().map(|e| e.time_us);
+    unsorted_events("bgm_events", bgm, &mut errors);
+    unsorted_events("visual_events", visual, &mut errors);
+    unsorted_events("speed_events", speed, &mut errors);
+
+    errors
+}
+
+fn validate_note(note_index: usize, note: &Note) -> Result<(), ChartIntegrityError> {
+    if note.col >= LANE_COUNT {
+        return Err(ChartIntegrityError::LaneOutOfRange {
+            note_index,
+            col: note.col,
+        });
+    }
+
+    let (kind, scratch_only, keys_only) = match &note.kind {
+        NoteKind::Tap => ("tap", false, false),
+        NoteKind::ChargeNote { .. } => ("cn", false, true),
+        NoteKind::HellChargeNote { .. } => ("hcn", false, true),
+        NoteKind::BackSpinScratch { .. } => ("bss", true, false),
+        NoteKind::HellBackSpinScratch { .. } => ("hbss", true, false),
+        NoteKind::MultiSpinScratch { .. } => ("mss", true, false),
+        NoteKind::HellMultiSpinScratch { .. } => ("hmss", true, false),
+    };
+    if (scratch_only && note.col != 0) || (keys_only && note.col == 0) {
+        return Err(ChartIntegrityError::KindNotAllowedOnLane {
+            note_index,
+            col: note.col,
+            kind,
+        });
+    }
+
+    let Some(end_time_us) = note.kind.end_time_us() else {
+        return Ok(());
+    };
+    if end_time_us <= note.time_us {
+        return Err(ChartIntegrityError::HoldEndNotAfterStart {
+            note_index,
+            time_us: note.time_us,
+            end_time_us,
+        });
+    }
+
+    if let NoteKind::MultiSpinScratch {
+        reverse_checkpoints_us,
+        ..
+    }
+    | NoteKind::HellMultiSpinScratch {
+        reverse_checkpoints_us,
+        ..
+    } = &note.kind
+    {
+        let mut prev_cp: Option<Microseconds> = None;
+        for &checkpoint_us in reverse_checkpoints_us {
+            if checkpoint_us <= note.time_us || checkpoint_us >= end_time_us {
+                return Err(ChartIntegrityError::CheckpointOutsideHold {
+                    note_index,
+                    checkpoint_us,
+                    time_us: note.time_us,
+                    end_time_us,
+                });
+            }
+            if prev_cp.is_some_and(|p| checkpoint_us <= p) {
+                return Err(ChartIntegrityError::UnsortedCheckpoints {
+                    note_index,
+                    checkpoint_us,
+                });
+            }
+            prev_cp = Some(checkpoint_us);
+        }
+    }
+
+    Ok(())
+}
+
+fn unsorted_events(
+    list: &'static str,
+    times: impl Iterator<Item = Microseconds>,
+    errors: &mut Vec<ChartIntegrityError>,
+) {
+    let mut prev_time_us: Microseconds = 0;
+    for (index, time_us) in times.enumerate() {
+        if time_us < prev_time_us {
+            errors.push(ChartIntegrityError::UnsortedEvents {
+                list,
+                index,
+                time_us,
+                prev_time_us,
+            });
+        }
+        prev_time_us = time_us;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdf_schema::{BgmEvent, SpeedEvent};
+
+    fn chart_with(notes: Vec<Note>) -> MdfChart {
+        MdfChart {
+            notes,
+            ..Default::default()
+        }
+    }
+
+    fn note(time_us: Microseconds, col: u8, kind: NoteKind) -> Note {
+        Note {
+            time_us,
+            col,
+            kind,
+            sound_id: None,
+        }
+    }
+
+    #[test]
+    fn valid_chart_passes() {
+        let chart = chart_with(vec![
+            note(0, 1, NoteKind::Tap),
+            note(0, 2, NoteKind::ChargeNote { end_time_us: 500 }),
+            note(
+                100,
+                0,
+                NoteKind::MultiSpinScratch {
+                    end_time_us: 400,
+                    reverse_checkpoints_us: vec![200, 300],
+                },
+            ),
+        ]);
+        assert_eq!(validate_chart(&chart), Ok(()));
+    }
+
+    #[test]
+    fn unsorted_notes_are_rejected() {
+        let chart = chart_with(vec![note(100, 1, NoteKind::Tap), note(50, 2, NoteKind::Tap)]);
+        assert_eq!(
+            validate_chart(&chart),
+            Err(ChartIntegrityError::UnsortedNotes {
+                note_index: 1,
+                time_us: 50,
+                prev_time_us: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn lane_out_of_range_is_rejected() {
+        let chart = chart_with(vec![note(0, 8, NoteKind::Tap)]);
+        assert_eq!(
+            validate_chart(&chart),
+            Err(ChartIntegrityError::LaneOutOfRange { note_index: 0, col: 8 })
+        );
+    }
+
+    #[test]
+    fn scratch_kinds_off_lane_zero_are_rejected() {
+        let chart = chart_with(vec![note(0, 3, NoteKind::BackSpinScratch { end_time_us: 10 })]);
+        let err = validate_chart(&chart).unwrap_err();
+        assert_eq!(
+            err,
+            ChartIntegrityError::KindNotAllowedOnLane {
+                note_index: 0,
+                col: 3,
+                kind: "bss",
+            }
+        );
+        assert!(err.to_string().contains("kind=bss"));
+
+        let chart = chart_with(vec![note(0, 0, NoteKind::HellChargeNote { end_time_us: 10 })]);
+        assert!(matches!(
+            validate_chart(&chart),
+            Err(ChartIntegrityError::KindNotAllowedOnLane { kind: "hcn", .. })
+        ));
+    }
+
+    #[test]
+    fn zero_length_hold_is_rejected() {
+        let chart = chart_with(vec![note(100, 1, NoteKind::ChargeNote { end_time_us: 100 })]);
+        assert_eq!(
+            validate_chart(&chart),
+            Err(ChartIntegrityError::HoldEndNotAfterStart {
+                note_index: 0,
+                time_us: 100,
+                end_time_us: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn mss_checkpoints_must_be_inside_and_ascending() {
+        let mss = |cps: Vec<Microseconds>| {
+            note(
+                100,
+                0,
+                NoteKind::HellMultiSpinScratch {
+                    end_time_us: 400,
+                    reverse_checkpoints_us: cps,
+                },
+            )
+        };
+
+        assert!(matches!(
+            validate_chart(&chart_with(vec![mss(vec![400])])),
+            Err(ChartIntegrityError::CheckpointOutsideHold { checkpoint_us: 400, .. })
+        ));
+        assert!(matches!(
+            validate_chart(&chart_with(vec![mss(vec![100])])),
+            Err(ChartIntegrityError::CheckpointOutsideHold { checkpoint_us: 100, .. })
+        ));
+        assert_eq!(
+            validate_chart(&chart_with(vec![mss(vec![300, 200])])),
+            Err(ChartIntegrityError::UnsortedCheckpoints {
+                note_index: 0,
+                checkpoint_us: 200,
+            })
+        );
+    }
+
+    #[test]
+    fn unsorted_events_are_rejected() {
+        let mut chart = chart_with(vec![note(0, 1, NoteKind::Tap)]);
+        chart.bgm_events = [0, 200, 100]
+            .map(|time_us| BgmEvent {
+                time_us,
+                sound_id: "B".to_string(),
+            })
+            .into();
+        chart.speed_events = [50, 0]
+            .map(|time_us| SpeedEvent {
+                time_us,
+                scroll_rate: 1.0,
+                suggested: true,
+            })
+            .into();
+
+        assert_eq!(
+            validate_chart_all(&chart),
+            vec![
+                ChartIntegrityError::UnsortedEvents {
+                    list: "bgm_events",
+                    index: 2,
+                    time_us: 100,
+                    prev_time_us: 200,
+                },
+                ChartIntegrityError::UnsortedEvents {
+                    list: "speed_events",
+                    index: 1,
+                    time_us: 0,
+                    prev_time_us: 50,
+                },
+            ]
+        );
+        assert!(validate_chart(&chart)
+            .unwrap_err()
+            .to_string()
+            .starts_with("bgm_events are not sorted by time_us (index=2"));
+    }
+
+    #[test]
+    fn validate_chart_all_reports_every_note() {
+        let chart = chart_with(vec![
+            note(100, 9, NoteKind::Tap),
+            note(50, 1, NoteKind::ChargeNote { end_time_us: 50 }),
+        ]);
+        assert_eq!(
+            validate_chart_all(&chart),
+            vec![
+                ChartIntegrityError::LaneOutOfRange { note_index: 0, col: 9 },
+                ChartIntegrityError::UnsortedNotes {
+                    note_index: 1,
+                    time_us: 50,
+                    prev_time_us: 100,
+                },
+                ChartIntegrityError::HoldEndNotAfterStart {
+                    note_index: 1,
+                    time_us: 50,
+                    end_time_us: 50,
+                },
+            ]
+        );
+        assert_eq!(validate_chart_all(&chart_with(Vec::new())), Vec::new());
+    }
+
+    #[test]
+    fn notes_inside_a_hold_on_the_same_lane_are_rejected() {
+        let chart = chart_with(vec![
+            note(0, 2, NoteKind::ChargeNote { end_time_us: 500 }),
+            note(0, 3, NoteKind::Tap),
+            note(100, 2, NoteKind::Tap),
+            note(300, 2, NoteKind::HellChargeNote { end_time_us: 900 }),
+            note(500, 3, NoteKind::Tap),
+            note(500, 3, NoteKind::Tap),
+            // the hold starting at 300 is still open
+            note(600, 2, NoteKind::Tap),
+            note(900, 2, NoteKind::Tap),
+        ]);
+        let overlap = |note_index, col, other_index| ChartIntegrityError::LaneOverlap {
+            note_index,
+            col,
+            other_index,
+        };
+        assert_eq!(
+            validate_chart_all(&chart),
+            vec![
+                overlap(2, 2, 0),
+                overlap(3, 2, 0),
+                overlap(5, 3, 4),
+                overlap(6, 2, 3),
+            ]
+        );
+    }
+}
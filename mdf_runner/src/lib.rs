@@ -3,6 +3,12 @@ use std::{fs, path::Path};
 use anyhow::Context;
 use mdf_schema::MdfChart;
 
+mod audit;
+mod integrity;
+
+pub use audit::{audit_resources, AudioFormat, MissingReason, MissingResource};
+pub use integrity::{validate_chart, validate_chart_all, ChartIntegrityError};
+
 pub fn load_chart_json_from_path(path: impl AsRef<Path>) -> anyhow::Result<MdfChart> {
     let path = path.as_ref();
     let bytes = fs::read(path).with_context(|| format!("failed to read chart: {}", path.display()))?;
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use crate::{BgmEvent, MdfChart, Microseconds, Note, NoteKind};
+
+/// Serialize a chart as pretty JSON with every object's keys sorted, including
+/// `resources`, so the same chart always produces byte-identical output.
+pub fn to_canonical_json(chart: &MdfChart) -> serde_json::Result<String> {
+    // serde_json's `Map` is a BTreeMap unless `preserve_order` is enabled.
+    let value = serde_json::to_value(chart)?;
+    serde_json::to_string_pretty(&value)
+}
+
+/// Compare two charts for golden tests, ignoring differences that do not change the chart:
+/// the order of notes sharing a `(time_us, col)` slot and of simultaneous BGM events.
+///
+/// Returns a description of the first difference, or `None` if the charts are equivalent.
+pub fn semantic_diff(a: &MdfChart, b: &MdfChart) -> Option<String> {
+    if a.meta != b.meta {
+        return Some(format!("meta differs: {:?} vs {:?}", a.meta, b.meta));
+    }
+
+    map_diff("resources", &a.resources, &b.resources)
+        .or_else(|| map_diff("resource_slices", &a.resource_slices, &b.resource_slices))
+        .or_else(|| first_diff("notes", &sorted_notes(a), &sorted_notes(b)))
+        .or_else(|| first_diff("bgm_events", &sorted_bgm(a), &sorted_bgm(b)))
+        .or_else(|| first_diff("visual_events", &a.visual_events, &b.visual_events))
+        .or_else(|| first_diff("speed_events", &a.speed_events, &b.speed_events))
+        .or_else(|| {
+            (a.analysis != b.analysis)
+                .then(|| format!("analysis differs: {:?} vs {:?}", a.analysis, b.analysis))
+        })
+}
+
+fn map_diff<V: PartialEq + Debug>(
+    what: &str,
+    a: &HashMap<String, V>,
+    b: &HashMap<String, V>,
+) -> Option<String> {
+    let mut ids: Vec<&String> = a.keys().chain(b.keys()).collect();
+    ids.sort();
+    ids.dedup();
+    ids.into_iter().find_map(|id| {
+        let (x, y) = (a.get(id), b.get(id));
+        (x != y).then(|| format!("{what}[{id:?}] differs: {x:?} vs {y:?}"))
+    })
+}
+
+fn sorted_notes(c: &MdfChart) -> Vec<&Note> {
+    let mut v: Vec<&Note> = c.notes.iter().collect();
+    v.sort_by(|x, y| note_key(x).cmp(&note_key(y)));
+    v
+}
+
+/// `(time_us, col, kind, end_time_us, reverse_checkpoints_us, sound_id)`
+type NoteKey<'a> = (
+    Microseconds,
+    u8,
+    u8,
+    Option<Microseconds>,
+    &'a [Microseconds],
+    Option<&'a str>,
+);
+
+/// Total order over every field of a note, so ties in `(time_us, col)` sort the same
+/// way regardless of input order.
+fn note_key(n: &Note) -> NoteKey<'_> {
+    let (rank, checkpoints): (u8, &[Microseconds]) = match &n.kind {
+        NoteKind::Tap => (0, &[]),
+        NoteKind::ChargeNote { .. } => (1, &[]),
+        NoteKind::HellChargeNote { .. } => (2, &[]),
+        NoteKind::BackSpinScratch { .. } => (3, &[]),
+        NoteKind::HellBackSpinScratch { .. } => (4, &[]),
+        NoteKind::MultiSpinScratch {
+            reverse_checkpoints_us,
+            ..
+        } => (5, reverse_checkpoints_us),
+        NoteKind::HellMultiSpinScratch {
+            reverse_checkpoints_us,
+            ..
+        } => (6, reverse_checkpoints_us),
+    };
+    (
+        n.time_us,
+        n.col,
+        rank,
+        n.kind.end_time_us(),
+        checkpoints,
+        n.sound_id.as_deref(),
+    )
+}
+
+fn sorted_bgm(c: &MdfChart) -> Vec<&BgmEvent> {
+    let mut v: Vec<&BgmEvent> = c.bgm_events.iter().collect();
+    v.sort_by(|x, y| (x.time_us, &x.sound_id).cmp(&(y.time_us, &y.sound_id)));
+    v
+}
+
+fn first_diff<T: PartialEq + Debug>(what: &str, a: &[T], b: &[T]) -> Option<String> {
+    if let Some(i) = a.iter().zip(b).position(|(x, y)| x != y) {
+        return Some(format!("{what}[{i}] differs: {:?} vs {:?}", a[i], b[i]));
+    }
+    (a.len() != b.len()).then(|| format!("{what} count differs: {} vs {}", a.len(), b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SoundSlice;
+
+    fn chart(notes: Vec<Note>, resources: &[(&str, &str)]) -> MdfChart {
+        MdfChart {
+            resources: resources
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            notes,
+            bgm_events: vec![
+                BgmEvent {
+                    time_us: 0,
+                    sound_id: "A".to_string(),
+                },
+                BgmEvent {
+                    time_us: 0,
+                    sound_id: "B".to_string(),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn tap(time_us: u64, col: u8) -> Note {
+        Note {
+            time_us,
+            col,
+            kind: NoteKind::Tap,
+            sound_id: None,
+        }
+    }
+
+    #[test]
+    fn canonical_json_sorts_resource_keys() {
+        let resources: Vec<(String, String)> = (0..32)
+            .rev()
+            .map(|i| (format!("K{i:02}"), "k.wav".to_string()))
+            .collect();
+        let mut c = chart(vec![tap(0, 1)], &[]);
+        c.resources = resources.into_iter().collect::<HashMap<_, _>>();
+
+        let json = to_canonical_json(&c).unwrap();
+        let positions: Vec<usize> = (0..32)
+            .map(|i| json.find(&format!("\"K{i:02}\"")).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert!(json.find("\"bgm_events\"").unwrap() < json.find("\"meta\"").unwrap());
+    }
+
+    #[test]
+    fn semantic_diff_ignores_order_of_simultaneous_events() {
+        let a = chart(vec![tap(0, 1), tap(0, 2), tap(500, 1)], &[("K01", "a.wav")]);
+        let mut b = chart(vec![tap(0, 2), tap(0, 1), tap(500, 1)], &[("K01", "a.wav")]);
+        b.bgm_events.reverse();
+        assert_eq!(semantic_diff(&a, &b), None);
+
+        // same slot, told apart only by kind, hold end or sound
+        let slot = || {
+            let mut keyed = tap(0, 1);
+            keyed.sound_id = Some("K01".to_string());
+            let mut cn = tap(0, 1);
+            cn.kind = NoteKind::ChargeNote { end_time_us: 100 };
+            let mut longer = tap(0, 1);
+            longer.kind = NoteKind::ChargeNote { end_time_us: 200 };
+            vec![tap(0, 1), keyed, cn, longer]
+        };
+        let a = chart(slot(), &[]);
+        let mut b = chart(slot(), &[]);
+        b.notes.reverse();
+        assert_eq!(semantic_diff(&a, &b), None);
+        b.notes[0].sound_id = Some("K02".to_string());
+        assert!(semantic_diff(&a, &b).unwrap().starts_with("notes["));
+    }
+
+    #[test]
+    fn semantic_diff_reports_first_difference() {
+        let a = chart(vec![tap(0, 1), tap(500, 1)], &[("K01", "a.wav")]);
+
+        let b = chart(vec![tap(0, 1), tap(501, 1)], &[("K01", "a.wav")]);
+        assert!(semantic_diff(&a, &b)
+            .unwrap()
+            .starts_with("notes[1] differs"));
+
+        let c = chart(vec![tap(0, 1)], &[("K01", "a.wav")]);
+        assert_eq!(
+            semantic_diff(&a, &c).unwrap(),
+            "notes count differs: 2 vs 1"
+        );
+
+        let d = chart(vec![tap(0, 1), tap(500, 1)], &[("K01", "b.wav")]);
+        assert!(semantic_diff(&a, &d)
+            .unwrap()
+            .starts_with("resources[\"K01\"] differs"));
+
+        let mut e = chart(vec![tap(0, 1), tap(500, 1)], &[("K01", "a.wav")]);
+        e.resource_slices.insert(
+            "K01".to_string(),
+            SoundSlice {
+                start_ms: 0,
+                len_ms: 250,
+            },
+        );
+        assert_eq!(
+            semantic_diff(&a, &e).unwrap(),
+            "resource_slices[\"K01\"] differs: None vs Some(SoundSlice { start_ms: 0, len_ms: 250 })"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use sha2::{Digest, Sha256};
+
+use crate::{MdfChart, Note, NoteKind};
+
+/// Version tag mixed into the digest. Bump when the canonical encoding changes.
+const CHECKSUM_VERSION: &[u8] = b"mdf-chart-checksum/v1\n";
+
+/// Compute the gameplay checksum of a chart.
+///
+/// Only judgement-relevant data is hashed: for every note its `time_us`, `col`, kind,
+/// `end_time_us` and `reverse_checkpoints_us`. Metadata, resources, sound ids,
+/// `bgm_events`, `visual_events` and `speed_events` are ignored, so editing a title or
+/// re-mapping keysounds keeps the checksum stable.
+///
+/// Notes are encoded as fixed-width big-endian records and sorted before hashing, so the
+/// result does not depend on the order of notes sharing a timestamp.
+///
+/// Returns the SHA-256 digest as 64 lowercase hex chars.
+pub fn checksum(chart: &MdfChart) -> String {
+    let mut records: Vec<Vec<u8>> = chart.notes.iter().map(encode_note).collect();
+    records.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    hasher.update(CHECKSUM_VERSION);
+    hasher.update((records.len() as u64).to_be_bytes());
+    for r in &records {
+        hasher.update(r);
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn encode_note(note: &Note) -> Vec<u8> {
+    let (tag, checkpoints): (u8, &[u64]) = match &note.kind {
+        NoteKind::Tap => (0, &[]),
+        NoteKind::ChargeNote { .. } => (1, &[]),
+        NoteKind::HellChargeNote { .. } => (2, &[]),
+        NoteKind::BackSpinScratch { .. } => (3, &[]),
+        NoteKind::HellBackSpinScratch { .. } => (4, &[]),
+        NoteKind::MultiSpinScratch {
+            reverse_checkpoints_us,
+            ..
+        } => (5, reverse_checkpoints_us),
+        NoteKind::HellMultiSpinScratch {
+            reverse_checkpoints_us,
+            ..
+        } => (6, reverse_checkpoints_us),
+    };
+
+    let mut out = Vec::with_capacity(26 + checkpoints.len() * 8);
+    out.extend_from_slice(&note.time_us.to_be_bytes());
+    out.push(note.col);
+    out.push(tag);
+    out.extend_from_slice(&note.kind.end_time_us().unwrap_or(0).to_be_bytes());
+    out.extend_from_slice(&(checkpoints.len() as u64).to_be_bytes());
+    for cp in checkpoints {
+        out.extend_from_slice(&cp.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BgmEvent;
+
+    fn chart(notes: Vec<Note>) -> MdfChart {
+        MdfChart {
+            notes,
+            ..Default::default()
+        }
+    }
+
+    fn tap(time_us: u64, col: u8, sound_id: Option<&str>) -> Note {
+        Note {
+            time_us,
+            col,
+            kind: NoteKind::Tap,
+            sound_id: sound_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn checksum_is_hex_sha256() {
+        let sum = checksum(&chart(vec![tap(0, 1, None)]));
+        assert_eq!(sum.len(), 64);
+        assert!(sum.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')));
+    }
+
+    #[test]
+    fn checksum_ignores_metadata_sounds_and_bgm() {
+        let a = chart(vec![tap(0, 1, Some("K01")), tap(500, 2, None)]);
+        let mut b = chart(vec![tap(0, 1, None), tap(500, 2, Some("K02"))]);
+        b.meta.title = "renamed".to_string();
+        b.resources.insert("K02".to_string(), "k.wav".to_string());
+        b.bgm_events.push(BgmEvent {
+            time_us: 100,
+            sound_id: "K02".to_string(),
+        });
+        assert_eq!(checksum(&a), checksum(&b));
+    }
+
+    #[test]
+    fn checksum_ignores_order_of_simultaneous_notes() {
+        let a = chart(vec![tap(0, 1, None), tap(0, 2, None)]);
+        let b = chart(vec![tap(0, 2, None), tap(0, 1, None)]);
+        assert_eq!(checksum(&a), checksum(&b));
+    }
+
+    #[test]
+    fn checksum_changes_with_timing_lane_and_kind() {
+        let base = checksum(&chart(vec![tap(0, 1, None)]));
+        assert_ne!(base, checksum(&chart(vec![tap(1, 1, None)])));
+        assert_ne!(base, checksum(&chart(vec![tap(0, 2, None)])));
+
+        let cn = Note {
+            time_us: 0,
+            col: 1,
+            kind: NoteKind::ChargeNote { end_time_us: 0 },
+            sound_id: None,
+        };
+        assert_ne!(base, checksum(&chart(vec![cn])));
+
+        let mss = |cps: Vec<u64>| Note {
+            time_us: 0,
+            col: 0,
+            kind: NoteKind::MultiSpinScratch {
+                end_time_us: 400,
+                reverse_checkpoints_us: cps,
+            },
+            sound_id: None,
+        };
+        assert_ne!(
+            checksum(&chart(vec![mss(vec![200])])),
+            checksum(&chart(vec![mss(vec![300])]))
+        );
+    }
+}
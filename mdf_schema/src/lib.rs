@@ -1,20 +1,40 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+mod canonical;
+mod checksum;
+mod transform;
+
+pub use canonical::{semantic_diff, to_canonical_json};
+pub use checksum::checksum;
+pub use transform::TransformError;
+
 pub type Microseconds = u64;
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct MdfChart {
     pub meta: Metadata,
     #[serde(default)]
     pub resources: HashMap<String, String>,
+    /// Keysounds that play only part of their `resources` file, keyed by sound_id.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub resource_slices: HashMap<String, SoundSlice>,
     pub visual_events: Vec<VisualEvent>,
     pub speed_events: Vec<SpeedEvent>,
     pub notes: Vec<Note>,
     pub bgm_events: Vec<BgmEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<ChartAnalysis>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// Region of a shared audio file (e.g. one long BGM) used as a single keysound.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SoundSlice {
+    pub start_ms: u64,
+    pub len_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct Metadata {
     pub title: String,
     pub artist: String,
@@ -36,6 +56,9 @@ pub struct VisualEvent {
 pub struct SpeedEvent {
     pub time_us: Microseconds,
     pub scroll_rate: f64,
+    /// Chart-recommended hi-speed (`@scroll_hint`); applied only when the player opts in.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suggested: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -100,6 +123,22 @@ pub struct BgmEvent {
     pub sound_id: String,
 }
 
+/// Precomputed summary for song select (density graph, BPM range). Derived from the
+/// chart itself, so it never affects playback.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChartAnalysis {
+    /// Notes starting in each second: entry `i` counts `[i s, i + 1 s)`.
+    pub density_curve: Vec<u32>,
+    /// Tempo at the track start, then at every change.
+    pub bpm_timeline: Vec<BpmPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BpmPoint {
+    pub time_us: Microseconds,
+    pub bpm: f64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,6 +183,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn speed_event_suggested_defaults_false() {
+        let v = serde_json::json!({ "time_us": 0, "scroll_rate": 1.5 });
+        let ev: SpeedEvent = serde_json::from_value(v.clone()).unwrap();
+        assert!(!ev.suggested);
+        assert_eq!(serde_json::to_value(&ev).unwrap(), v);
+    }
+
     #[test]
     fn chart_roundtrip_minimal() {
         let mut resources = HashMap::new();
@@ -158,6 +205,7 @@ mod tests {
                 tags: vec!["training".to_string()],
             },
             resources,
+            resource_slices: HashMap::new(),
             visual_events: vec![],
             speed_events: vec![],
             notes: vec![Note {
@@ -170,9 +218,11 @@ mod tests {
                 time_us: 500,
                 sound_id: "SE_END".to_string(),
             }],
+            analysis: None,
         };
 
         let json = serde_json::to_string(&chart).unwrap();
+        assert!(!json.contains("analysis"));
         let back: MdfChart = serde_json::from_str(&json).unwrap();
         assert_eq!(chart, back);
     }
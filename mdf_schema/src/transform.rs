@@ -0,0 +1,339 @@
+use std::fmt;
+
+use crate::{MdfChart, Microseconds, NoteKind};
+
+/// Why a chart transform was rejected. The chart is left unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformError {
+    /// Shifting would move the event at `time_us` before 0 or past `u64::MAX`.
+    OutOfRange { time_us: Microseconds, offset_us: i64 },
+    /// Tempo factors must be finite and greater than 0.
+    InvalidFactor(f64),
+    /// Scaling by `factor` would move the event at `time_us` past `u64::MAX`.
+    ScaledOutOfRange { time_us: Microseconds, factor: f64 },
+    /// Rounding after scaling by `factor` would make the hold at `note_index` end at or
+    /// before its start, or put an MSS checkpoint on the start, the end or another checkpoint.
+    HoldCollapsed { note_index: usize, factor: f64 },
+    /// Rounding after scaling by `factor` would put the note at `note_index` on the same
+    /// microsecond as the earlier note at `other_index` on the same lane.
+    NotesCollapsed {
+        note_index: usize,
+        other_index: usize,
+        factor: f64,
+    },
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange { time_us, offset_us } => {
+                write!(f, "shift by {offset_us}us moves time_us={time_us} out of range")
+            }
+            Self::InvalidFactor(factor) => write!(f, "tempo factor must be > 0, got {factor}"),
+            Self::ScaledOutOfRange { time_us, factor } => {
+                write!(f, "tempo factor {factor} moves time_us={time_us} out of range")
+            }
+            Self::HoldCollapsed { note_index, factor } => {
+                write!(f, "tempo factor {factor} collapses the hold at note_index={note_index}")
+            }
+            Self::NotesCollapsed {
+                note_index,
+                other_index,
+                factor,
+            } => write!(
+                f,
+                "tempo factor {factor} moves note_index={note_index} onto note_index={other_index} on the same lane"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TransformError {}
+
+impl MdfChart {
+    /// Move every timed value (notes, hold ends, MSS checkpoints, BGM, visual and speed
+    /// events, `total_duration_us`) by `offset_us`, e.g. to realign with a new audio render.
+    ///
+    /// `analysis` is dropped since its per-second buckets no longer line up.
+    pub fn shift(&mut self, offset_us: i64) -> Result<(), TransformError> {
+        let mut bad = None;
+        self.for_each_time(|t| {
+            if bad.is_none() && t.checked_add_signed(offset_us).is_none() {
+                bad = Some(*t);
+            }
+        });
+        if let Some(time_us) = bad {
+            return Err(TransformError::OutOfRange { time_us, offset_us });
+        }
+
+        self.for_each_time(|t| *t = t.wrapping_add_signed(offset_us));
+        self.analysis = None;
+        Ok(())
+    }
+
+    /// Play the chart `factor` times as fast: every timed value is divided by `factor`
+    /// (rounded to the nearest microsecond) and `visual_events` BPMs are multiplied by it.
+    /// Scroll rates are unchanged.
+    ///
+    /// Fails if a scaled time would not fit in `u64`, or if rounding would collapse a hold
+    /// (see `TransformError::HoldCollapsed`) or merge two notes on one lane
+    /// (`TransformError::NotesCollapsed`); charts that already had either are left as is.
+    ///
+    /// `analysis` is dropped since it describes the original tempo.
+    pub fn scale_tempo(&mut self, factor: f64) -> Result<(), TransformError> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(TransformError::InvalidFactor(factor));
+        }
+        let scale = |t: Microseconds| (t as f64 / factor).round();
+
+        let mut bad = None;
+        self.for_each_time(|t| {
+            // u64::MAX rounds up to 2^64 as f64, so `>=` also catches values the cast would saturate
+            if bad.is_none() && scale(*t) >= u64::MAX as f64 {
+                bad = Some(*t);
+            }
+        });
+        if let Some(time_us) = bad {
+            return Err(TransformError::ScaledOutOfRange { time_us, factor });
+        }
+
+        for (note_index, n) in self.notes.iter().enumerate() {
+            let Some(end_time_us) = n.kind.end_time_us() else {
+                continue;
+            };
+            let checkpoints: &[Microseconds] = match &n.kind {
+                NoteKind::MultiSpinScratch {
+                    reverse_checkpoints_us,
+                    ..
+                }
+                | NoteKind::HellMultiSpinScratch {
+                    reverse_checkpoints_us,
+                    ..
+                } => reverse_checkpoints_us,
+                _ => &[],
+            };
+            let points: Vec<Microseconds> = std::iter::once(n.time_us)
+                .chain(checkpoints.iter().copied())
+                .chain([end_time_us])
+                .collect();
+            if points
+                .windows(2)
+                .any(|w| w[0] < w[1] && scale(w[0]) >= scale(w[1]))
+            {
+                return Err(TransformError::HoldCollapsed { note_index, factor });
+            }
+        }
+
+        let mut starts: Vec<(u8, Microseconds, usize)> = self
+            .notes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.col, n.time_us, i))
+            .collect();
+        starts.sort_unstable();
+        for w in starts.windows(2) {
+            let ((col_a, time_a, other_index), (col_b, time_b, note_index)) = (w[0], w[1]);
+            if col_a == col_b && time_a < time_b && scale(time_a) == scale(time_b) {
+                return Err(TransformError::NotesCollapsed {
+                    note_index,
+                    other_index,
+                    factor,
+                });
+            }
+        }
+
+        self.for_each_time(|t| *t = scale(*t) as Microseconds);
+        for e in &mut self.visual_events {
+            e.bpm *= factor;
+        }
+        self.analysis = None;
+        Ok(())
+    }
+
+    fn for_each_time(&mut self, mut f: impl FnMut(&mut Microseconds)) {
+        f(&mut self.meta.total_duration_us);
+        for n in &mut self.notes {
+            f(&mut n.time_us);
+            match &mut n.kind {
+                NoteKind::Tap => {}
+                NoteKind::ChargeNote { end_time_us }
+                | NoteKind::HellChargeNote { end_time_us }
+                | NoteKind::BackSpinScratch { end_time_us }
+                | NoteKind::HellBackSpinScratch { end_time_us } => f(end_time_us),
+                NoteKind::MultiSpinScratch {
+                    end_time_us,
+                    reverse_checkpoints_us,
+                }
+                | NoteKind::HellMultiSpinScratch {
+                    end_time_us,
+                    reverse_checkpoints_us,
+                } => {
+                    f(end_time_us);
+                    reverse_checkpoints_us.iter_mut().for_each(&mut f);
+                }
+            }
+        }
+        self.bgm_events.iter_mut().for_each(|e| f(&mut e.time_us));
+        self.visual_events.iter_mut().for_each(|e| f(&mut e.time_us));
+        self.speed_events.iter_mut().for_each(|e| f(&mut e.time_us));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BgmEvent, Metadata, Note, SpeedEvent, VisualEvent};
+
+    fn chart() -> MdfChart {
+        MdfChart {
+            meta: Metadata {
+                total_duration_us: 3_000,
+                ..Default::default()
+            },
+            visual_events: vec![VisualEvent {
+                time_us: 500,
+                bpm: 150.0,
+                is_measure_line: false,
+                beat_n: 0,
+                beat_d: 0,
+            }],
+            speed_events: vec![SpeedEvent {
+                time_us: 1_000,
+                scroll_rate: 2.0,
+                suggested: true,
+            }],
+            notes: vec![
+                Note {
+                    time_us: 1_000,
+                    col: 0,
+                    kind: NoteKind::MultiSpinScratch {
+                        end_time_us: 3_000,
+                        reverse_checkpoints_us: vec![2_000],
+                    },
+                    sound_id: None,
+                },
+                Note {
+                    time_us: 1_500,
+                    col: 2,
+                    kind: NoteKind::ChargeNote { end_time_us: 2_500 },
+                    sound_id: None,
+                },
+            ],
+            bgm_events: vec![BgmEvent {
+                time_us: 500,
+                sound_id: "BGM".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shift_moves_every_timed_value() {
+        let mut c = chart();
+        c.shift(-500).unwrap();
+
+        assert_eq!(c.meta.total_duration_us, 2_500);
+        assert_eq!(c.bgm_events[0].time_us, 0);
+        assert_eq!(c.visual_events[0].time_us, 0);
+        assert_eq!(c.speed_events[0].time_us, 500);
+        assert_eq!(
+            c.notes[0].kind,
+            NoteKind::MultiSpinScratch {
+                end_time_us: 2_500,
+                reverse_checkpoints_us: vec![1_500],
+            }
+        );
+        assert_eq!(c.notes[1].time_us, 1_000);
+        assert_eq!(c.notes[1].kind.end_time_us(), Some(2_000));
+    }
+
+    #[test]
+    fn shift_before_zero_is_rejected_without_changes() {
+        let mut c = chart();
+        assert_eq!(
+            c.shift(-501),
+            Err(TransformError::OutOfRange {
+                time_us: 500,
+                offset_us: -501
+            })
+        );
+        assert_eq!(c, chart());
+    }
+
+    #[test]
+    fn scale_tempo_divides_times_and_multiplies_bpm() {
+        let mut c = chart();
+        c.scale_tempo(1.5).unwrap();
+
+        assert_eq!(c.meta.total_duration_us, 2_000);
+        assert_eq!(c.visual_events[0].bpm, 225.0);
+        assert_eq!(c.speed_events[0].scroll_rate, 2.0);
+        assert_eq!(c.speed_events[0].time_us, 667);
+        assert_eq!(c.notes[1].kind.end_time_us(), Some(1_667));
+
+        assert_eq!(c.scale_tempo(0.0), Err(TransformError::InvalidFactor(0.0)));
+    }
+
+    #[test]
+    fn scale_tempo_out_of_range_is_rejected_without_changes() {
+        let mut c = chart();
+        assert_eq!(
+            c.scale_tempo(1e-300),
+            Err(TransformError::ScaledOutOfRange {
+                time_us: 3_000,
+                factor: 1e-300
+            })
+        );
+        assert_eq!(c, chart());
+    }
+
+    #[test]
+    fn scale_tempo_that_collapses_a_hold_is_rejected() {
+        let mut c = chart();
+        // 1_000..3_000 with a checkpoint at 2_000: 0.25..0.75 -> 0..1 keeps the span,
+        // but 0.5 rounds onto the end
+        assert_eq!(
+            c.scale_tempo(4_000.0),
+            Err(TransformError::HoldCollapsed {
+                note_index: 0,
+                factor: 4_000.0
+            })
+        );
+        assert_eq!(c, chart());
+
+        // already-degenerate holds are not this transform's problem
+        let mut c = chart();
+        c.notes[1].kind = NoteKind::ChargeNote { end_time_us: 1_500 };
+        c.scale_tempo(1.5).unwrap();
+        assert_eq!(c.notes[1].kind.end_time_us(), Some(1_000));
+    }
+
+    #[test]
+    fn scale_tempo_that_merges_notes_on_a_lane_is_rejected() {
+        let tap = |time_us, col| Note {
+            time_us,
+            col,
+            kind: NoteKind::Tap,
+            sound_id: None,
+        };
+        let taps = |notes: &[(Microseconds, u8)]| MdfChart {
+            notes: notes.iter().map(|&(t, col)| tap(t, col)).collect(),
+            ..Default::default()
+        };
+        // 3_100 and 3_200 both round to 3 at factor 1_000; 3_300 on another lane is fine
+        let mut c = taps(&[(1_000, 4), (3_200, 4), (3_100, 4), (3_300, 5)]);
+        assert_eq!(
+            c.scale_tempo(1_000.0),
+            Err(TransformError::NotesCollapsed {
+                note_index: 1,
+                other_index: 2,
+                factor: 1_000.0
+            })
+        );
+        assert_eq!(c, taps(&[(1_000, 4), (3_200, 4), (3_100, 4), (3_300, 5)]));
+
+        // notes that already shared a time are not this transform's problem
+        let mut c = taps(&[(3_100, 4), (3_100, 4)]);
+        c.scale_tempo(2.0).unwrap();
+    }
+}
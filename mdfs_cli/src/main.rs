@@ -1,15 +1,22 @@
 use std::{
     fs,
+    io::IsTerminal,
     path::{Path, PathBuf},
 };
 
 use anyhow::Context;
 use clap::{Parser, Subcommand};
+use mdfs_compiler::{CompileOptions, LintConfig};
+use tracing_subscriber::{filter::LevelFilter, fmt::format::FmtSpan};
 
 #[derive(Debug, Parser)]
 #[command(name = "mdfs")]
 #[command(about = "MDFS compiler CLI", long_about = None)]
 struct Cli {
+    /// Log compiler passes to stderr (-v: timings, manifests, counts; -vv: more detail)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -20,28 +27,135 @@ enum Command {
         input: PathBuf,
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Skip the playability lints (warnings only; they never fail the compile)
+        #[arg(long)]
+        no_lint: bool,
+        /// Add the density curve / BPM timeline `analysis` section to the output
+        #[arg(long)]
+        emit_analysis: bool,
+        /// Byte-stable output: sorted object keys and resources (for golden files)
+        #[arg(long)]
+        deterministic: bool,
+    },
+    /// Check a compiled .mdf.json (e.g. from a third party) and print a report
+    Verify {
+        chart: PathBuf,
+        /// Directory `resources` paths are relative to (default: the chart's directory)
+        #[arg(long)]
+        base_dir: Option<PathBuf>,
+        /// Skip the audit of resource files on disk (integrity checks still run)
+        #[arg(long, conflicts_with = "base_dir")]
+        no_audio: bool,
     },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    init_logging(cli.verbose);
 
     match cli.command {
-        Command::Compile { input, output } => {
-            let chart = mdfs_compiler::compile_file(&input)
+        Command::Compile {
+            input,
+            output,
+            no_lint,
+            emit_analysis,
+            deterministic,
+        } => {
+            let options = CompileOptions {
+                lints: (!no_lint).then(LintConfig::default),
+                emit_analysis,
+                ..Default::default()
+            };
+            let (chart, warnings) = mdfs_compiler::compile_file_with_warnings(&input, options)
                 .map_err(|e| anyhow::anyhow!(e.to_string()))
                 .with_context(|| format!("compile failed: {}", input.display()))?;
+            for w in &warnings {
+                eprintln!("warning: {w} [step {}, {}us]", w.step_index, w.time_us);
+            }
 
-            let json = serde_json::to_string_pretty(&chart).context("failed to serialize mdf")?;
+            let json = if deterministic {
+                mdf_schema::to_canonical_json(&chart)
+            } else {
+                serde_json::to_string_pretty(&chart)
+            }
+            .context("failed to serialize mdf")?;
             let out_path = output.unwrap_or_else(|| default_output_path(&input));
             fs::write(&out_path, json)
                 .with_context(|| format!("failed to write: {}", out_path.display()))?;
         }
+        Command::Verify {
+            chart,
+            base_dir,
+            no_audio,
+        } => verify(&chart, base_dir, no_audio)?,
     }
 
     Ok(())
 }
 
+/// Integrity (sorted notes and events, lanes, hold spans, MSS checkpoints) plus resource audit.
+/// Prints every finding, then fails if there was any. `no_audio` skips the resource audit.
+fn verify(path: &Path, base_dir: Option<PathBuf>, no_audio: bool) -> anyhow::Result<()> {
+    let chart = mdf_runner::load_chart_json_from_path(path)?;
+    let base_dir = base_dir
+        .or_else(|| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    let integrity = mdf_runner::validate_chart_all(&chart);
+    if integrity.is_empty() {
+        println!("integrity: ok");
+    } else {
+        println!("integrity: FAIL {} problem(s)", integrity.len());
+        for e in &integrity {
+            println!("  {e}");
+        }
+    }
+
+    let missing = if no_audio {
+        Vec::new()
+    } else {
+        mdf_runner::audit_resources(&chart, &base_dir)
+    };
+    if no_audio {
+        println!("resources: skipped");
+    } else if missing.is_empty() {
+        println!("resources: ok");
+    } else {
+        println!("resources: FAIL {} unusable", missing.len());
+        for m in &missing {
+            let path = m.path.as_deref().map(|p| p.display().to_string());
+            println!(
+                "  {}: {:?} path={} notes={} bgm_events={}",
+                m.sound_id,
+                m.reason,
+                path.as_deref().unwrap_or("-"),
+                m.note_indices.len(),
+                m.bgm_event_indices.len()
+            );
+        }
+    }
+
+    if !integrity.is_empty() || !missing.is_empty() {
+        anyhow::bail!("verify failed: {}", path.display());
+    }
+    Ok(())
+}
+
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => return,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+}
+
 fn default_output_path(input: &Path) -> PathBuf {
     let mut out = input.to_path_buf();
     out.set_extension("mdf.json");
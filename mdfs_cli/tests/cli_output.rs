@@ -156,3 +156,221 @@ fn compile_output_write_failure_is_reported_stably() {
     assert!(stderr.contains("out.mdf.json"));
     assert!(stderr.contains("Caused by:"));
 }
+
+#[test]
+fn verbose_flag_logs_passes_to_stderr() {
+    let exe = env!("CARGO_BIN_EXE_mdfs_cli");
+
+    let dir = env::temp_dir().join(format!(
+        "oxidizer_mdfs_cli_verbose_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let input = dir.join("in.mdfs");
+    let output_path = dir.join("out.mdf.json");
+    fs::write(
+        &input,
+        "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  ..N.....\n",
+    )
+    .unwrap();
+
+    let run = |flag: Option<&str>| {
+        let mut cmd = Command::new(exe);
+        cmd.args(flag);
+        cmd.args([
+            "compile",
+            input.to_str().unwrap(),
+            "-o",
+            output_path.to_str().unwrap(),
+        ]);
+        let out = cmd.output().unwrap();
+        assert!(out.status.success());
+        norm_newlines(&String::from_utf8_lossy(&out.stderr))
+    };
+
+    assert_eq!(run(None), "");
+
+    let info = run(Some("-v"));
+    assert!(info.contains("pass2"));
+    assert!(info.contains("time.busy"));
+    assert!(info.contains("notes=1"));
+    assert!(!info.contains("time map built"));
+
+    let debug = run(Some("-vv"));
+    assert!(debug.contains("time map built"));
+}
+
+/// Golden check for `--deterministic`. Regenerate after an intended output change with
+/// `cargo run -p mdfs_cli -- compile examples/mixed_long.mdfs --deterministic -o mdfs_cli/tests/golden/mixed_long.mdf.json`.
+#[test]
+fn deterministic_output_matches_golden() {
+    let exe = env!("CARGO_BIN_EXE_mdfs_cli");
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let dir = env::temp_dir().join(format!(
+        "oxidizer_mdfs_cli_deterministic_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let compile = |name: &str| {
+        let output_path = dir.join(name);
+        let out = Command::new(exe)
+            .args([
+                "compile",
+                root.join("../examples/mixed_long.mdfs").to_str().unwrap(),
+                "--deterministic",
+                "-o",
+                output_path.to_str().unwrap(),
+            ])
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        fs::read_to_string(output_path).unwrap()
+    };
+
+    let first = compile("a.mdf.json");
+    assert_eq!(first, compile("b.mdf.json"));
+
+    let golden = norm_newlines(&fs::read_to_string(root.join("tests/golden/mixed_long.mdf.json")).unwrap());
+    if norm_newlines(&first) != golden {
+        let actual: mdf_schema::MdfChart = serde_json::from_str(&first).unwrap();
+        let expected: mdf_schema::MdfChart = serde_json::from_str(&golden).unwrap();
+        match mdf_schema::semantic_diff(&expected, &actual) {
+            Some(diff) => panic!("compiled chart differs from golden: {diff}"),
+            None => panic!("compiled chart is equivalent to golden but not byte-identical"),
+        }
+    }
+}
+
+#[test]
+fn verify_reports_ok_for_compiled_chart() {
+    let exe = env!("CARGO_BIN_EXE_mdfs_cli");
+
+    let dir = env::temp_dir().join(format!(
+        "oxidizer_mdfs_cli_verify_ok_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let input = dir.join("in.mdfs");
+    let chart = dir.join("in.mdf.json");
+    fs::write(
+        &input,
+        "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  .l......\n  .l......\n",
+    )
+    .unwrap();
+    assert!(Command::new(exe)
+        .args(["compile", input.to_str().unwrap()])
+        .status()
+        .unwrap()
+        .success());
+
+    let out = Command::new(exe)
+        .args(["verify", chart.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert!(out.status.success());
+    let stdout = norm_newlines(&String::from_utf8_lossy(&out.stdout));
+    assert_eq!(stdout, "integrity: ok\nresources: ok\n");
+}
+
+#[test]
+fn verify_reports_every_problem_and_fails() {
+    let exe = env!("CARGO_BIN_EXE_mdfs_cli");
+
+    let dir = env::temp_dir().join(format!(
+        "oxidizer_mdfs_cli_verify_fail_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let chart = dir.join("broken.mdf.json");
+    fs::write(
+        &chart,
+        r#"{
+  "meta": {"title":"T","artist":"A","version":"2.2","total_duration_us":0,"tags":[]},
+  "resources": {"K01": "missing.wav"},
+  "visual_events": [],
+  "speed_events": [
+    {"time_us":500,"scroll_rate":2.0,"suggested":true},
+    {"time_us":0,"scroll_rate":1.0,"suggested":true}
+  ],
+  "notes": [
+    {"time_us":100,"col":1,"type":"cn","end_time_us":100,"sound_id":"K01"},
+    {"time_us":200,"col":9,"type":"tap"}
+  ],
+  "bgm_events": [{"time_us":0,"sound_id":"BGM"}]
+}"#,
+    )
+    .unwrap();
+
+    let out = Command::new(exe)
+        .args(["verify", chart.to_str().unwrap()])
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    let stdout = norm_newlines(&String::from_utf8_lossy(&out.stdout));
+    assert!(stdout.contains("integrity: FAIL 3 problem(s)\n"));
+    assert!(stdout.contains("  hold must end after it starts (note_index=0"));
+    assert!(stdout.contains("  lane out of range (note_index=1, col=9)\n"));
+    assert!(stdout.contains(
+        "  speed_events are not sorted by time_us (index=1, time_us=0, prev_time_us=500)\n"
+    ));
+    assert!(stdout.contains("resources: FAIL 2 unusable"));
+    assert!(stdout.contains("  BGM: NotInManifest path=- notes=0 bgm_events=1\n"));
+    assert!(stdout.contains("  K01: NotFound path="));
+    let stderr = norm_newlines(&String::from_utf8_lossy(&out.stderr));
+    assert!(stderr.contains("Error: verify failed:"));
+}
+
+#[test]
+fn verify_no_audio_skips_resource_audit_but_not_integrity() {
+    let exe = env!("CARGO_BIN_EXE_mdfs_cli");
+
+    let dir = env::temp_dir().join(format!(
+        "oxidizer_mdfs_cli_verify_no_audio_{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    let chart = dir.join("dist.mdf.json");
+    let write_chart = |notes: &str| {
+        fs::write(
+            &chart,
+            format!(
+                r#"{{
+  "meta": {{"title":"T","artist":"A","version":"2.2","total_duration_us":0,"tags":[]}},
+  "resources": {{"K01": "not_shipped.wav"}},
+  "visual_events": [],
+  "speed_events": [],
+  "notes": [{notes}],
+  "bgm_events": []
+}}"#
+            ),
+        )
+        .unwrap();
+    };
+    let verify = || {
+        Command::new(exe)
+            .args(["verify", "--no-audio", chart.to_str().unwrap()])
+            .output()
+            .unwrap()
+    };
+
+    write_chart(r#"{"time_us":0,"col":1,"type":"tap","sound_id":"K01"}"#);
+    let out = verify();
+    assert!(out.status.success());
+    let stdout = norm_newlines(&String::from_utf8_lossy(&out.stdout));
+    assert_eq!(stdout, "integrity: ok\nresources: skipped\n");
+
+    write_chart(r#"{"time_us":0,"col":9,"type":"tap","sound_id":"K01"}"#);
+    let out = verify();
+    assert_eq!(out.status.code(), Some(1));
+    let stdout = norm_newlines(&String::from_utf8_lossy(&out.stdout));
+    assert!(stdout.contains("  lane out of range (note_index=0, col=9)\n"));
+    assert!(stdout.ends_with("resources: skipped\n"));
+}
@@ -0,0 +1,90 @@
+//! Compiler throughput on a synthetic 50k-step chart (editor live-recompile sized).
+//!
+//! Run with `cargo bench -p mdfs_compiler --features bench`.
+
+use std::fmt::Write as _;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use mdfs_compiler::{bench, compile_str_with_options, CompileOptions, FileLoader};
+
+const STEPS: usize = 51_200;
+
+/// Taps with per-lane keysounds, CN/HCN toggles and MSS with `@rev_every` and `!` markers.
+fn synthetic_chart(steps: usize) -> String {
+    let mut src = String::from(
+        "@title Bench\n@artist Bench\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @div 16\n",
+    );
+    for i in 0..steps {
+        if i % 1024 == 0 {
+            writeln!(src, "  @bpm {}", 150 + (i / 1024) % 8).unwrap();
+        }
+        let mut cells = ['.'; 8];
+        match i % 64 {
+            8 | 40 => cells[0] = 'm',
+            24 => cells[0] = '!',
+            _ => {}
+        }
+        if matches!(i % 16, 4 | 12) {
+            cells[1] = 'l';
+        }
+        if i % 16 == 0 {
+            cells[3] = 'N';
+            cells[6] = 'N';
+        }
+        if i % 8 == 2 {
+            cells[4] = 'N';
+        }
+        if matches!(i % 32, 6 | 22) {
+            cells[7] = 'h';
+        }
+
+        src.push_str("  ");
+        src.extend(cells);
+        if cells.iter().any(|&c| c != '.') {
+            let slots: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(lane, &c)| match c {
+                    '.' => "-".to_string(),
+                    _ => format!("K{lane}{}", i % 4),
+                })
+                .collect();
+            write!(src, " : [{}]", slots.join(",")).unwrap();
+        }
+        if i % 64 == 8 {
+            src.push_str(" @rev_every 4");
+        }
+        src.push('\n');
+    }
+    src
+}
+
+fn options() -> CompileOptions {
+    let manifest: String = (0..8)
+        .flat_map(|lane| (0..4).map(move |v| format!("\"K{lane}{v}\":\"k{lane}{v}.wav\"")))
+        .collect::<Vec<_>>()
+        .join(",");
+    let manifest = format!("{{{manifest}}}").into_bytes();
+    CompileOptions {
+        file_loader: Some(FileLoader::new(move |_| Ok(manifest.clone()))),
+        ..Default::default()
+    }
+}
+
+fn compile_50k(c: &mut Criterion) {
+    let src = synthetic_chart(STEPS);
+    let options = options();
+    let prepared = bench::prepare(&src, &options).unwrap();
+
+    let mut group = c.benchmark_group("compile_50k_steps");
+    group.throughput(Throughput::Elements(STEPS as u64));
+    group.bench_function("parse", |b| b.iter(|| bench::parse(black_box(&src)).unwrap()));
+    group.bench_function("pass2", |b| b.iter(|| bench::pass2(black_box(&prepared)).unwrap()));
+    group.bench_function("compile_str", |b| {
+        b.iter(|| compile_str_with_options(black_box(&src), options.clone()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compile_50k);
+criterion_main!(benches);
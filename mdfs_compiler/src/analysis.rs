@@ -0,0 +1,59 @@
+use mdf_schema::{BpmPoint, ChartAnalysis, Microseconds, Note};
+
+use crate::parser::{Directive, TrackLine};
+
+pub(crate) fn analyze(
+    track: &[TrackLine],
+    starts: &[Microseconds],
+    durs: &[Microseconds],
+    notes: &[Note],
+    total_duration_us: Microseconds,
+) -> ChartAnalysis {
+    ChartAnalysis {
+        density_curve: density_curve(notes, total_duration_us),
+        bpm_timeline: bpm_timeline(track, starts, durs),
+    }
+}
+
+/// Note starts per whole second, covering `0..=total_duration_us`.
+fn density_curve(notes: &[Note], total_duration_us: Microseconds) -> Vec<u32> {
+    if notes.is_empty() {
+        return Vec::new();
+    }
+    let mut curve = vec![0u32; (total_duration_us / 1_000_000) as usize + 1];
+    for n in notes {
+        let sec = (n.time_us / 1_000_000) as usize;
+        if let Some(count) = curve.get_mut(sec) {
+            *count += 1;
+        }
+    }
+    curve
+}
+
+/// `@bpm` takes effect from the next step, mirroring pass 1. Repeats of the current
+/// tempo are dropped, and only the last of several changes between two steps is kept.
+fn bpm_timeline(track: &[TrackLine], starts: &[Microseconds], durs: &[Microseconds]) -> Vec<BpmPoint> {
+    let end_us = starts.last().zip(durs.last()).map_or(0, |(s, d)| s + d);
+    let mut points: Vec<BpmPoint> = Vec::new();
+    let mut step = 0;
+
+    for line in track {
+        match line {
+            TrackLine::Directive {
+                directive: Directive::Bpm(bpm),
+                ..
+            } => {
+                let time_us = starts.get(step).copied().unwrap_or(end_us);
+                if points.last().is_some_and(|p| p.time_us == time_us) {
+                    points.pop();
+                }
+                if points.last().is_none_or(|p| p.bpm != *bpm) {
+                    points.push(BpmPoint { time_us, bpm: *bpm });
+                }
+            }
+            TrackLine::Directive { .. } => {}
+            TrackLine::Step { .. } => step += 1,
+        }
+    }
+    points
+}
@@ -15,8 +15,8 @@ impl CompileErrorKind {
         // Spec: docs/MDFS_DSL-and-Compiler_Spec.md#6.2
         match code {
             // Parse
-            "E1001" | "E1002" | "E1003" | "E1004" | "E1005" | "E1006" | "E1101" | "E3201" | "E3202"
-            | "E3203" | "E3204" => Self::Parse,
+            "E1001" | "E1002" | "E1003" | "E1004" | "E1005" | "E1006" | "E1007" | "E1101" | "E3201"
+            | "E3202" | "E3203" | "E3204" => Self::Parse,
 
             // IO
             "E2001" | "E2002" | "E2003" | "E2004" => Self::IO,
@@ -25,10 +25,10 @@ impl CompileErrorKind {
             "E2101" | "E4201" => Self::Semantic,
 
             // TimeMap
-            "E3001" | "E3002" | "E3003" | "E3004" | "E3005" => Self::TimeMap,
+            "E3001" | "E3002" | "E3003" | "E3004" | "E3005" | "E3006" => Self::TimeMap,
 
             // Validation
-            "E4001" | "E4002" | "E4003" | "E4004" | "E4101" | "E4102" => Self::Validation,
+            "E4001" | "E4002" | "E4003" | "E4004" | "E4101" | "E4102" | "E4103" | "E4104" => Self::Validation,
 
             // MVP default: treat unknown codes as Parse.
             _ => Self::Parse,
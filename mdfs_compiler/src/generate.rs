@@ -1,9 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use mdf_schema::{BgmEvent, Microseconds, Note, NoteKind};
 
 use crate::CompileError;
-use crate::parser::{RevSpec, SoundSpec, TrackLine};
+use crate::parser::{Directive, RevSpec, SoundRef, SoundSpec, TrackLine};
 
 #[derive(Debug, Clone)]
 enum OpenHoldKind {
@@ -20,7 +20,7 @@ struct OpenHold {
     start_line: usize,
     start_time_us: Microseconds,
     start_step_index: usize,
-    sound_id: Option<String>,
+    sound_id: Option<SoundRef>,
     kind: OpenHoldKind,
     marker_checkpoints_us: Vec<Microseconds>,
 }
@@ -31,16 +31,50 @@ enum StartKind {
     HoldStart,
 }
 
+/// Interned sound_ids, checked against the manifest once per id rather than per reference.
+struct Sounds<'a> {
+    ids: &'a [&'a str],
+    resources: &'a HashMap<String, String>,
+    validated: Vec<bool>,
+}
+
+impl<'a> Sounds<'a> {
+    fn new(ids: &'a [&'a str], resources: &'a HashMap<String, String>) -> Self {
+        Self {
+            ids,
+            resources,
+            validated: vec![false; ids.len()],
+        }
+    }
+
+    fn name(&self, id: SoundRef) -> &'a str {
+        self.ids[id as usize]
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn validate(&mut self, id: SoundRef, line: usize, lane: Option<usize>) -> Result<(), CompileError> {
+        if !self.validated[id as usize] {
+            validate_sound_id(self.resources, self.name(id), line, lane)?;
+            self.validated[id as usize] = true;
+        }
+        Ok(())
+    }
+
+    fn owned(&self, id: Option<SoundRef>) -> Option<String> {
+        id.map(|id| self.name(id).to_string())
+    }
+}
+
 fn register_tap_start(
-    start_kinds: &mut HashMap<(Microseconds, u8), StartKind>,
+    start_kinds: &mut [Option<StartKind>; 8],
     time_us: Microseconds,
     lane_u8: u8,
     lane_for_message: usize,
     step_index: usize,
     line: usize,
 ) -> Result<(), CompileError> {
-    if let Some(existing) = start_kinds.get(&(time_us, lane_u8)) {
-        if *existing == StartKind::HoldStart {
+    if let Some(existing) = start_kinds[lane_u8 as usize] {
+        if existing == StartKind::HoldStart {
             return Err(
                 CompileError::new(
                     "E4004",
@@ -58,20 +92,20 @@ fn register_tap_start(
         return Ok(());
     }
 
-    start_kinds.insert((time_us, lane_u8), StartKind::Tap);
+    start_kinds[lane_u8 as usize] = Some(StartKind::Tap);
     Ok(())
 }
 
 fn register_hold_start(
-    start_kinds: &mut HashMap<(Microseconds, u8), StartKind>,
+    start_kinds: &mut [Option<StartKind>; 8],
     time_us: Microseconds,
     lane_u8: u8,
     lane_for_message: usize,
     step_index: usize,
     line: usize,
 ) -> Result<(), CompileError> {
-    if let Some(existing) = start_kinds.get(&(time_us, lane_u8)) {
-        if *existing == StartKind::Tap {
+    if let Some(existing) = start_kinds[lane_u8 as usize] {
+        if existing == StartKind::Tap {
             return Err(
                 CompileError::new(
                     "E4004",
@@ -89,7 +123,7 @@ fn register_hold_start(
         return Ok(());
     }
 
-    start_kinds.insert((time_us, lane_u8), StartKind::HoldStart);
+    start_kinds[lane_u8 as usize] = Some(StartKind::HoldStart);
     Ok(())
 }
 
@@ -99,7 +133,7 @@ fn handle_marker_checkpoint(
     time_us: Microseconds,
     step_index: usize,
     sound: &SoundSpec,
-    resources: &HashMap<String, String>,
+    sounds: &mut Sounds,
     line: usize,
 ) -> Result<(), CompileError> {
     // marker checkpoint only valid inside MSS/HMSS hold
@@ -120,7 +154,7 @@ fn handle_marker_checkpoint(
     match open0.kind {
         OpenHoldKind::Mss { .. } | OpenHoldKind::HellMss { .. } => {
             open0.marker_checkpoints_us.push(time_us);
-            push_bgm_events_from_sound(bgm_events, time_us, sound, resources, line)
+            push_bgm_events_from_sound(bgm_events, time_us, sound, sounds, line)
         }
         OpenHoldKind::Bss | OpenHoldKind::HellBss => Err(
             CompileError::new(
@@ -150,17 +184,35 @@ fn handle_marker_checkpoint(
 pub(crate) fn pass2_generate(
     track: &[TrackLine],
     step_times: &[Microseconds],
+    sound_ids: &[&str],
     resources: &HashMap<String, String>,
 ) -> Result<(Vec<Note>, Vec<BgmEvent>), CompileError> {
-    let mut notes = Vec::new();
+    let mut sounds = Sounds::new(sound_ids, resources);
+    // Taps are pushed in time order, holds when they close; merged once at the end.
+    let mut notes = Vec::with_capacity(step_times.len());
+    let mut holds = Vec::new();
     let mut bgm_events = Vec::new();
-    let mut start_kinds: HashMap<(Microseconds, u8), StartKind> = HashMap::new();
+    // Step times never decrease, so only starts at the current time can collide:
+    // per-lane start kinds, cleared whenever the time changes.
+    let mut start_kinds: [Option<StartKind>; 8] = [None; 8];
+    let mut start_kinds_time_us: Option<Microseconds> = None;
 
     let mut open: Vec<Option<OpenHold>> = vec![None; 8];
     let mut step_index = 0usize;
+    // @lane_sound defaults, used where the step's SOUND_SPEC gives the lane no sound
+    let mut lane_defaults: [Option<SoundRef>; 8] = [None; 8];
 
     for line in track {
         match line {
+            TrackLine::Directive {
+                line,
+                directive: Directive::LaneSound { lane, sound },
+            } => {
+                if let Some(id) = *sound {
+                    sounds.validate(id, *line, Some(*lane))?;
+                }
+                lane_defaults[*lane] = *sound;
+            }
             TrackLine::Directive { .. } => {}
             TrackLine::Step {
                 line,
@@ -173,12 +225,16 @@ pub(crate) fn pass2_generate(
                     .copied()
                     .ok_or_else(|| CompileError::new("E1101", "internal step index mismatch", *line))?;
 
-                let lane_sounds = lane_sounds(sound);
+                if start_kinds_time_us != Some(time_us) {
+                    start_kinds = [None; 8];
+                    start_kinds_time_us = Some(time_us);
+                }
+
                 let has_any_note = cells.iter().any(|c| !matches!(c, '.'));
 
                 // If step has only '.' but has SOUND_SPEC, generate BGM events (optional feature in spec)
                 if !has_any_note {
-                    push_bgm_events_from_sound(&mut bgm_events, time_us, sound, resources, *line)?;
+                    push_bgm_events_from_sound(&mut bgm_events, time_us, sound, &mut sounds, *line)?;
                 }
 
                 // Validate @rev directives appear only on MSS/HMSS start lines.
@@ -200,8 +256,9 @@ pub(crate) fn pass2_generate(
                     match ch {
                         '.' => {}
                         'N' | 'S' => {
-                            if let Some(id) = lane_sounds[col].as_deref() {
-                                validate_sound_id(resources, id, *line, Some(col))?;
+                            let sound_id = lane_sound(sound, col).or(lane_defaults[col]);
+                            if let Some(id) = sound_id {
+                                sounds.validate(id, *line, Some(col))?;
                             }
 
                             let lane_u8 = col as u8;
@@ -218,7 +275,7 @@ pub(crate) fn pass2_generate(
                                 time_us,
                                 col: col as u8,
                                 kind: NoteKind::Tap,
-                                sound_id: lane_sounds[col].clone(),
+                                sound_id: sounds.owned(sound_id),
                             });
                         }
                         'l' => {
@@ -236,13 +293,13 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_hold(
-                                &mut notes,
+                                &mut holds,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 col,
                                 time_us,
                                 step_index,
-                                lane_sounds[col].clone(),
+                                lane_sound(sound, col).or(lane_defaults[col]),
                                 OpenHoldKind::Charge,
                                 *line,
                             )?
@@ -262,13 +319,13 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_hold(
-                                &mut notes,
+                                &mut holds,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 col,
                                 time_us,
                                 step_index,
-                                lane_sounds[col].clone(),
+                                lane_sound(sound, col).or(lane_defaults[col]),
                                 OpenHoldKind::HellCharge,
                                 *line,
                             )?
@@ -288,14 +345,14 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_scratch_hold_end_se(
-                                &mut notes,
+                                &mut holds,
                                 &mut bgm_events,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 time_us,
                                 step_index,
                                 sound,
-                                lane_sounds[0].clone(),
+                                lane_sound(sound, 0).or(lane_defaults[0]),
                                 OpenHoldKind::Bss,
                                 *line,
                             )?
@@ -315,14 +372,14 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_scratch_hold_end_se(
-                                &mut notes,
+                                &mut holds,
                                 &mut bgm_events,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 time_us,
                                 step_index,
                                 sound,
-                                lane_sounds[0].clone(),
+                                lane_sound(sound, 0).or(lane_defaults[0]),
                                 OpenHoldKind::HellBss,
                                 *line,
                             )?
@@ -342,14 +399,14 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_mss(
-                                &mut notes,
+                                &mut holds,
                                 &mut bgm_events,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 time_us,
                                 step_index,
                                 sound,
-                                lane_sounds[0].clone(),
+                                lane_sound(sound, 0).or(lane_defaults[0]),
                                 OpenHoldKind::Mss { rev: rev.clone() },
                                 step_times,
                                 *line,
@@ -370,14 +427,14 @@ pub(crate) fn pass2_generate(
                             }
 
                             toggle_mss(
-                                &mut notes,
+                                &mut holds,
                                 &mut bgm_events,
                                 &mut open,
-                                resources,
+                                &mut sounds,
                                 time_us,
                                 step_index,
                                 sound,
-                                lane_sounds[0].clone(),
+                                lane_sound(sound, 0).or(lane_defaults[0]),
                                 OpenHoldKind::HellMss { rev: rev.clone() },
                                 step_times,
                                 *line,
@@ -390,7 +447,7 @@ pub(crate) fn pass2_generate(
                                 time_us,
                                 step_index,
                                 sound,
-                                resources,
+                                &mut sounds,
                                 *line,
                             )?;
                         }
@@ -425,14 +482,30 @@ pub(crate) fn pass2_generate(
         }
     }
 
-    Ok((notes, bgm_events))
+    holds.sort_by_key(|n: &Note| n.time_us);
+    Ok((merge_by_time(notes, holds), bgm_events))
+}
+
+/// Merge two lists sorted by `time_us`, `taps` first on ties. Holds start before the
+/// step that closes them, so this matches a stable sort of the notes in push order.
+fn merge_by_time(taps: Vec<Note>, holds: Vec<Note>) -> Vec<Note> {
+    let mut out = Vec::with_capacity(taps.len() + holds.len());
+    let mut holds = holds.into_iter().peekable();
+    for tap in taps {
+        while let Some(h) = holds.next_if(|h| h.time_us < tap.time_us) {
+            out.push(h);
+        }
+        out.push(tap);
+    }
+    out.extend(holds);
+    out
 }
 
-fn lane_sounds(sound: &SoundSpec) -> [Option<String>; 8] {
+fn lane_sound(sound: &SoundSpec, col: usize) -> Option<SoundRef> {
     match sound {
-        SoundSpec::None => std::array::from_fn(|_| None),
-        SoundSpec::Single(id) => std::array::from_fn(|_| Some(id.clone())),
-        SoundSpec::PerLane(lanes) => lanes.clone(),
+        SoundSpec::None => None,
+        SoundSpec::Single(id) => Some(*id),
+        SoundSpec::PerLane(lanes) => lanes[col],
     }
 }
 
@@ -489,26 +562,26 @@ fn push_bgm_events_from_sound(
     out: &mut Vec<BgmEvent>,
     time_us: Microseconds,
     sound: &SoundSpec,
-    resources: &HashMap<String, String>,
+    sounds: &mut Sounds,
     line: usize,
 ) -> Result<(), CompileError> {
     match sound {
         SoundSpec::None => Ok(()),
         SoundSpec::Single(id) => {
-            validate_sound_id(resources, id, line, None)?;
+            sounds.validate(*id, line, None)?;
             out.push(BgmEvent {
                 time_us,
-                sound_id: id.clone(),
+                sound_id: sounds.name(*id).to_string(),
             });
             Ok(())
         }
         SoundSpec::PerLane(lanes) => {
             for (lane, id) in lanes.iter().enumerate() {
-                let Some(id) = id else { continue };
-                validate_sound_id(resources, id, line, Some(lane))?;
+                let Some(id) = *id else { continue };
+                sounds.validate(id, line, Some(lane))?;
                 out.push(BgmEvent {
                     time_us,
-                    sound_id: id.clone(),
+                    sound_id: sounds.name(id).to_string(),
                 });
             }
             Ok(())
@@ -519,11 +592,11 @@ fn push_bgm_events_from_sound(
 fn toggle_hold(
     notes: &mut Vec<Note>,
     open: &mut [Option<OpenHold>],
-    resources: &HashMap<String, String>,
+    sounds: &mut Sounds,
     col: usize,
     time_us: Microseconds,
     step_index: usize,
-    sound_id: Option<String>,
+    sound_id: Option<SoundRef>,
     kind: OpenHoldKind,
     line: usize,
 ) -> Result<(), CompileError> {
@@ -531,10 +604,10 @@ fn toggle_hold(
         return Err(CompileError::new("E4001", "CN/HCN not allowed on scratch", line));
     }
 
-    match &open[col] {
+    match open[col].take() {
         None => {
-            if let Some(id) = sound_id.as_deref() {
-                validate_sound_id(resources, id, line, Some(col))?;
+            if let Some(id) = sound_id {
+                sounds.validate(id, line, Some(col))?;
             }
             open[col] = Some(OpenHold {
                 start_line: line,
@@ -547,7 +620,7 @@ fn toggle_hold(
         }
         Some(existing) => {
             let (start_time_us, sound_id, existing_kind) =
-                (existing.start_time_us, existing.sound_id.clone(), existing.kind.clone());
+                (existing.start_time_us, existing.sound_id, existing.kind);
             match (&existing_kind, &kind) {
                 (OpenHoldKind::Charge, OpenHoldKind::Charge)
                 | (OpenHoldKind::HellCharge, OpenHoldKind::HellCharge) => {}
@@ -560,6 +633,7 @@ fn toggle_hold(
                 }
             }
 
+            check_hold_span(start_time_us, time_us, col, step_index, line)?;
             let note_kind = match existing_kind {
                 OpenHoldKind::Charge => NoteKind::ChargeNote { end_time_us: time_us },
                 OpenHoldKind::HellCharge => NoteKind::HellChargeNote { end_time_us: time_us },
@@ -570,9 +644,8 @@ fn toggle_hold(
                 time_us: start_time_us,
                 col: col as u8,
                 kind: note_kind,
-                sound_id,
+                sound_id: sounds.owned(sound_id),
             });
-            open[col] = None;
         }
     }
     Ok(())
@@ -582,17 +655,17 @@ fn toggle_scratch_hold_end_se(
     notes: &mut Vec<Note>,
     bgm_events: &mut Vec<BgmEvent>,
     open: &mut [Option<OpenHold>],
-    resources: &HashMap<String, String>,
+    sounds: &mut Sounds,
     time_us: Microseconds,
     step_index: usize,
     end_sound: &SoundSpec,
-    start_sound_id: Option<String>,
+    start_sound_id: Option<SoundRef>,
     kind: OpenHoldKind,
     line: usize,
 ) -> Result<(), CompileError> {
     if open[0].is_none() {
-        if let Some(id) = start_sound_id.as_deref() {
-            validate_sound_id(resources, id, line, Some(0))?;
+        if let Some(id) = start_sound_id {
+            sounds.validate(id, line, Some(0))?;
         }
         open[0] = Some(OpenHold {
             start_line: line,
@@ -622,8 +695,10 @@ fn toggle_scratch_hold_end_se(
         }
     }
 
+    check_hold_span(start_time_us, time_us, 0, step_index, line)?;
+
     // end line SOUND_SPEC -> BgmEvent(s)
-    push_bgm_events_from_sound(bgm_events, time_us, end_sound, resources, line)?;
+    push_bgm_events_from_sound(bgm_events, time_us, end_sound, sounds, line)?;
 
     let note_kind = match existing_kind {
         OpenHoldKind::Bss => NoteKind::BackSpinScratch { end_time_us: time_us },
@@ -634,7 +709,7 @@ fn toggle_scratch_hold_end_se(
         time_us: start_time_us,
         col: 0,
         kind: note_kind,
-        sound_id,
+        sound_id: sounds.owned(sound_id),
     });
 
     Ok(())
@@ -644,19 +719,19 @@ fn toggle_mss(
     notes: &mut Vec<Note>,
     bgm_events: &mut Vec<BgmEvent>,
     open: &mut [Option<OpenHold>],
-    resources: &HashMap<String, String>,
+    sounds: &mut Sounds,
     time_us: Microseconds,
     step_index: usize,
     end_sound: &SoundSpec,
-    start_sound_id: Option<String>,
+    start_sound_id: Option<SoundRef>,
     kind: OpenHoldKind,
     step_times: &[Microseconds],
     line: usize,
 ) -> Result<(), CompileError> {
     if open[0].is_none() {
         // start
-        if let Some(id) = start_sound_id.as_deref() {
-            validate_sound_id(resources, id, line, Some(0))?;
+        if let Some(id) = start_sound_id {
+            sounds.validate(id, line, Some(0))?;
         }
         open[0] = Some(OpenHold {
             start_line: line,
@@ -700,8 +775,10 @@ fn toggle_mss(
         }
     }
 
+    check_hold_span(start_time_us, time_us, 0, step_index, line)?;
+
     // end line SOUND_SPEC -> BgmEvent(s)
-    push_bgm_events_from_sound(bgm_events, time_us, end_sound, resources, line)?;
+    push_bgm_events_from_sound(bgm_events, time_us, end_sound, sounds, line)?;
 
     let checkpoints = compute_mss_checkpoints(
         start_step,
@@ -728,12 +805,36 @@ fn toggle_mss(
         time_us: start_time_us,
         col: 0,
         kind: note_kind,
-        sound_id,
+        sound_id: sounds.owned(sound_id),
     });
 
     Ok(())
 }
 
+/// Holds must end strictly after they start, or downstream judges see a zero/negative span.
+#[allow(clippy::result_large_err)]
+fn check_hold_span(
+    start_time_us: Microseconds,
+    end_time_us: Microseconds,
+    col: usize,
+    step_index: usize,
+    line: usize,
+) -> Result<(), CompileError> {
+    if end_time_us > start_time_us {
+        return Ok(());
+    }
+    Err(CompileError::new(
+        "E4103",
+        format!(
+            "hold on lane={col} ends at time_us={end_time_us}, not after its start at time_us={start_time_us}"
+        ),
+        line,
+    )
+    .with_step_index(step_index)
+    .with_time_us(end_time_us)
+    .with_lane(col as u8))
+}
+
 fn compute_mss_checkpoints(
     start_step: usize,
     end_step: usize,
@@ -747,19 +848,15 @@ fn compute_mss_checkpoints(
         return Err(CompileError::new("E4101", "invalid MSS toggle range", line));
     }
 
-    let mut set = HashSet::<Microseconds>::new();
-    for &t in marker_us {
-        if t != end_time_us {
-            set.insert(t);
-        }
-    }
+    // collected then sorted and deduped; a hold has few checkpoints, so no set needed
+    let mut v: Vec<Microseconds> = marker_us.iter().copied().filter(|&t| t != end_time_us).collect();
 
     if let Some(n) = rev.every {
         let mut idx = start_step + n;
         while idx < end_step {
             if let Some(&t) = step_times.get(idx) {
                 if t != end_time_us {
-                    set.insert(t);
+                    v.push(t);
                 }
             }
             idx += n;
@@ -772,18 +869,33 @@ fn compute_mss_checkpoints(
         if idx < end_step {
             if let Some(&t) = step_times.get(idx) {
                 if t != end_time_us {
-                    set.insert(t);
+                    v.push(t);
                 }
             }
         }
     }
 
-    let mut v: Vec<Microseconds> = set.into_iter().collect();
     v.sort_unstable();
+    v.dedup();
+    let start_time_us = step_times.get(start_step).copied().unwrap_or(0);
+    if let Some(&t) = v.iter().find(|&&t| t <= start_time_us || t >= end_time_us) {
+        return Err(CompileError::new(
+            "E4104",
+            format!(
+                "MSS checkpoint at time_us={t} is outside the hold span ({start_time_us}, {end_time_us})"
+            ),
+            line,
+        )
+        .with_time_us(t)
+        .with_lane(0));
+    }
     Ok(v)
 }
 
-pub(crate) fn compute_total_duration_us(notes: &[Note], bgm_events: &[BgmEvent]) -> Microseconds {
+pub(crate) fn compute_total_duration_us(
+    notes: &[Note],
+    bgm_events: &[BgmEvent],
+) -> Microseconds {
     let mut max_us: Microseconds = 0;
     for n in notes {
         let end = match &n.kind {
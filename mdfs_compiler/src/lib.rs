@@ -1,21 +1,30 @@
 use std::{
-    fs,
+    collections::HashMap,
+    fmt, io,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use mdf_schema::{Metadata, MdfChart, SpeedEvent, VisualEvent};
+use mdf_schema::{Metadata, MdfChart, VisualEvent};
+use tracing::{debug, info, info_span};
 
+mod analysis;
 mod error;
 mod generate;
+mod lint;
 mod parser;
 mod resources;
 mod time_map;
 
 pub use error::{CompileError, CompileErrorKind};
+pub use lint::{CompileWarning, LintConfig};
 
 /// Options for compilation.
 ///
-/// MVP: currently only controls how relative paths (e.g. `@sound_manifest`) are resolved.
+/// - Where external files (e.g. `@sound_manifest`) are found and how they are read
+///   (`base_dir`, `file_loader`), or a manifest supplied in memory (`inline_resources`).
+/// - Which playability lints run (`lints`), for the `*_with_warnings()` functions.
+/// - Whether `MdfChart::analysis` is filled (`emit_analysis`).
 #[derive(Debug, Clone, Default)]
 pub struct CompileOptions {
     /// Base directory used to resolve relative paths.
@@ -23,21 +32,117 @@ pub struct CompileOptions {
     /// - `compile_file()` sets this automatically to the input file's parent directory.
     /// - `compile_str()` uses `None` by default.
     pub base_dir: Option<PathBuf>,
+
+    /// Reads external files instead of `std::fs` (virtual filesystem hook).
+    ///
+    /// Required for `@sound_manifest` when built without the `fs` feature (e.g. wasm32).
+    /// If `base_dir` is `None`, the manifest path is passed to the loader as written.
+    pub file_loader: Option<FileLoader>,
+
+    /// Playability lints reported by the `*_with_warnings()` functions. `None` skips them;
+    /// functions that do not return warnings never run them.
+    pub lints: Option<LintConfig>,
+
+    /// Fill `MdfChart::analysis` (NPS density curve and BPM timeline) for song select.
+    pub emit_analysis: bool,
+
+    /// sound_id -> file map used in place of the `@sound_manifest` file.
+    ///
+    /// When set, the manifest path is never resolved or read (the directive may be omitted),
+    /// so editors and services can compile without any filesystem access.
+    pub inline_resources: Option<HashMap<String, String>>,
+}
+
+type LoadFn = dyn Fn(&Path) -> io::Result<Vec<u8>> + Send + Sync;
+
+/// Callback that returns the bytes of the file at `path`.
+#[derive(Clone)]
+pub struct FileLoader(Arc<LoadFn>);
+
+impl FileLoader {
+    pub fn new(f: impl Fn(&Path) -> io::Result<Vec<u8>> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn load(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (self.0)(path)
+    }
+}
+
+impl fmt::Debug for FileLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FileLoader(..)")
+    }
 }
 
 /// Compile an `.mdfs` file into an `MdfChart`.
 ///
 /// Returns `CompileError` on failure. Its `Display` output is stable and only includes
 /// `code`, `message` and `line` (structured fields are available separately).
+///
+/// Requires the `fs` feature (enabled by default).
+#[cfg(feature = "fs")]
 pub fn compile_file(path: impl AsRef<Path>) -> Result<MdfChart, CompileError> {
-    let path = path.as_ref();
-    let src = fs::read_to_string(path)
-           .map_err(|e| {
-               CompileError::new("E2001", format!("failed to read input .mdfs: {e}"), 0)
-                   .with_file(path.display().to_string())
-           })?;
-    let base_dir = path.parent().map(|p| p.to_path_buf());
-    compile_str_with_options(&src, CompileOptions { base_dir })
+    compile_path(path.as_ref(), CompileOptions::default(), None).map(|(chart, _)| chart)
+}
+
+/// Like `compile_file()`, with options, also returning lint warnings (see `CompileOptions::lints`).
+///
+/// Requires the `fs` feature (enabled by default).
+#[cfg(feature = "fs")]
+#[allow(clippy::result_large_err)]
+pub fn compile_file_with_warnings(
+    path: impl AsRef<Path>,
+    options: CompileOptions,
+) -> Result<(MdfChart, Vec<CompileWarning>), CompileError> {
+    compile_path(path.as_ref(), options, None)
+}
+
+/// Compile several `.mdfs` files in parallel (rayon's global thread pool).
+///
+/// Results are returned in the order of `paths`. As with `compile_file()`, each file's
+/// `base_dir` is its parent directory; the other `options` apply to every file. Charts
+/// that resolve to the same `@sound_manifest` file share a single read and parse of it.
+///
+/// Requires the `parallel` feature (enabled by default).
+#[cfg(feature = "parallel")]
+#[allow(clippy::result_large_err)]
+pub fn compile_many<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    options: &CompileOptions,
+) -> Vec<Result<MdfChart, CompileError>> {
+    use rayon::prelude::*;
+
+    let cache = resources::ManifestCache::default();
+    paths
+        .par_iter()
+        .map(|path| {
+            let options = CompileOptions {
+                lints: None,
+                ..options.clone()
+            };
+            compile_path(path.as_ref(), options, Some(&cache)).map(|(chart, _)| chart)
+        })
+        .collect()
+}
+
+#[cfg(feature = "fs")]
+#[allow(clippy::result_large_err)]
+fn compile_path(
+    path: &Path,
+    options: CompileOptions,
+    cache: Option<&resources::ManifestCache>,
+) -> Result<(MdfChart, Vec<CompileWarning>), CompileError> {
+    let _file = info_span!("file", path = %path.display()).entered();
+    let src = std::fs::read_to_string(path).map_err(|e| {
+        CompileError::new("E2001", format!("failed to read input .mdfs: {e}"), 0)
+            .with_file(path.display().to_string())
+    })?;
+    let options = CompileOptions {
+        base_dir: path.parent().map(|p| p.to_path_buf()),
+        ..options
+    };
+    compile(&src, &options, cache)
 }
 
 /// Compile `.mdfs` source text into an `MdfChart`.
@@ -47,41 +152,135 @@ pub fn compile_str(src: &str) -> Result<MdfChart, CompileError> {
 
 /// Compile `.mdfs` source text into an `MdfChart` with options.
 pub fn compile_str_with_options(src: &str, options: CompileOptions) -> Result<MdfChart, CompileError> {
-    let parsed = parser::parse_mdfs(src)?;
+    let options = CompileOptions {
+        lints: None,
+        ..options
+    };
+    compile(src, &options, None).map(|(chart, _)| chart)
+}
 
-    let resources = resources::load_resources(&parsed, &options)?;
-    let (step_times, _step_durations) = time_map::pass1_time_map(&parsed.track)?;
-    let (mut notes, mut bgm_events) = generate::pass2_generate(&parsed.track, &step_times, &resources)?;
+/// Like `compile_str_with_options()`, also returning lint warnings (see `CompileOptions::lints`).
+#[allow(clippy::result_large_err)]
+pub fn compile_str_with_warnings(
+    src: &str,
+    options: CompileOptions,
+) -> Result<(MdfChart, Vec<CompileWarning>), CompileError> {
+    compile(src, &options, None)
+}
+
+#[allow(clippy::result_large_err)]
+fn compile(
+    src: &str,
+    options: &CompileOptions,
+    cache: Option<&resources::ManifestCache>,
+) -> Result<(MdfChart, Vec<CompileWarning>), CompileError> {
+    // Spans are timed by the subscriber (`mdfs -v`); no clock here, so wasm32 stays supported.
+    let _compile = info_span!("compile").entered();
+
+    let parsed = info_span!("parse").in_scope(|| parser::parse_mdfs(src))?;
+    debug!(track_lines = parsed.track.len(), sound_ids = parsed.sound_ids.len(), "parsed");
+
+    let resources = info_span!("resources").in_scope(|| resources::load_resources(&parsed, options, cache))?;
+    let (step_times, step_durations) = info_span!("pass1").in_scope(|| time_map::pass1_time_map(&parsed.track))?;
+    debug!(steps = step_times.len(), "time map built");
+    let speed_events = time_map::scroll_hints(&parsed.track, &step_times);
+    let (notes, mut bgm_events) = info_span!("pass2").in_scope(|| {
+        generate::pass2_generate(&parsed.track, &step_times, &parsed.sound_ids, &resources.files)
+    })?;
+    info!(notes = notes.len(), bgm_events = bgm_events.len(), "generated");
 
-    notes.sort_by_key(|n| n.time_us);
     bgm_events.sort_by_key(|e| e.time_us);
 
+    let warnings = match &options.lints {
+        Some(config) => {
+            info_span!("lint").in_scope(|| lint::lint_notes(config, &notes, &step_times, &parsed.track))
+        }
+        None => Vec::new(),
+    };
+
     let total_duration_us = generate::compute_total_duration_us(&notes, &bgm_events);
     let meta = Metadata {
         title: parsed
             .meta
             .title
-            .ok_or_else(|| CompileError::new("E3201", "missing @title", parsed.meta_line))?,
+            .ok_or_else(|| CompileError::new("E3201", "missing @title", parsed.meta_line))?
+            .to_string(),
         artist: parsed
             .meta
             .artist
-            .ok_or_else(|| CompileError::new("E3202", "missing @artist", parsed.meta_line))?,
+            .ok_or_else(|| CompileError::new("E3202", "missing @artist", parsed.meta_line))?
+            .to_string(),
         version: parsed
             .meta
             .version
-            .ok_or_else(|| CompileError::new("E3203", "missing @version", parsed.meta_line))?,
-        tags: parsed.meta.tags,
+            .ok_or_else(|| CompileError::new("E3203", "missing @version", parsed.meta_line))?
+            .to_string(),
+        tags: parsed.meta.tags.iter().map(|t| t.to_string()).collect(),
         total_duration_us,
     };
+    let analysis = options.emit_analysis.then(|| {
+        analysis::analyze(&parsed.track, &step_times, &step_durations, &notes, total_duration_us)
+    });
 
-    Ok(MdfChart {
+    let chart = MdfChart {
         meta,
-        resources,
+        resources: resources.files,
+        resource_slices: resources.slices,
         visual_events: Vec::<VisualEvent>::new(),
-        speed_events: Vec::<SpeedEvent>::new(),
+        speed_events,
         notes,
         bgm_events,
-    })
+        analysis,
+    };
+    Ok((chart, warnings))
+}
+
+/// Per-pass entry points for `benches/`. Not part of the public API.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+#[allow(clippy::result_large_err)]
+pub mod bench {
+    use std::collections::HashMap;
+
+    use mdf_schema::Microseconds;
+
+    use crate::{generate, parser, resources, time_map, CompileError, CompileOptions};
+
+    /// Source parsed and timed, ready for pass 2.
+    pub struct Prepared<'a> {
+        track: Vec<parser::TrackLine>,
+        sound_ids: Vec<&'a str>,
+        step_times: Vec<Microseconds>,
+        resources: HashMap<String, String>,
+    }
+
+    /// Parse only. Returns the number of track lines.
+    pub fn parse(src: &str) -> Result<usize, CompileError> {
+        Ok(parser::parse_mdfs(src)?.track.len())
+    }
+
+    pub fn prepare<'a>(src: &'a str, options: &CompileOptions) -> Result<Prepared<'a>, CompileError> {
+        let parsed = parser::parse_mdfs(src)?;
+        let resources = resources::load_resources(&parsed, options, None)?;
+        let (step_times, _) = time_map::pass1_time_map(&parsed.track)?;
+        Ok(Prepared {
+            track: parsed.track,
+            sound_ids: parsed.sound_ids,
+            step_times,
+            resources: resources.files,
+        })
+    }
+
+    /// Pass 2 only. Returns the number of notes.
+    pub fn pass2(prepared: &Prepared<'_>) -> Result<usize, CompileError> {
+        let (notes, _) = generate::pass2_generate(
+            &prepared.track,
+            &prepared.step_times,
+            &prepared.sound_ids,
+            &prepared.resources,
+        )?;
+        Ok(notes.len())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use mdf_schema::{Microseconds, Note, NoteKind};
+
+use crate::parser::TrackLine;
+
+/// Thresholds for the playability lints. `None`/`false` turns a check off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    /// Most keys (lanes 1-7) that may be struck together with a scratch note (W5001).
+    pub max_keys_with_scratch: Option<usize>,
+    /// Fastest repeat rate on a single lane, in notes per second (W5002).
+    pub max_jack_nps: Option<f64>,
+    /// Warn when CN/HCN holds cover all seven key lanes at once (W5003).
+    pub full_keyboard_cn: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_keys_with_scratch: Some(5),
+            max_jack_nps: Some(20.0),
+            full_keyboard_cn: true,
+        }
+    }
+}
+
+/// A chart that compiles but is likely unplayable. Never fails the compile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileWarning {
+    pub code: &'static str,
+    pub message: String,
+    pub line: usize,
+    pub step_index: usize,
+    pub time_us: Microseconds,
+    pub lane: Option<u8>,
+}
+
+impl fmt::Display for CompileWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (line {})", self.code, self.message, self.line)
+    }
+}
+
+/// Maps note times back to the step (and source line) they came from.
+struct Steps<'a> {
+    times: &'a [Microseconds],
+    lines: Vec<usize>,
+}
+
+impl Steps<'_> {
+    fn warning(
+        &self,
+        code: &'static str,
+        message: String,
+        time_us: Microseconds,
+        lane: Option<u8>,
+    ) -> CompileWarning {
+        let step_index = self.times.partition_point(|&t| t < time_us);
+        CompileWarning {
+            code,
+            message,
+            line: self.lines.get(step_index).copied().unwrap_or(0),
+            step_index,
+            time_us,
+            lane,
+        }
+    }
+}
+
+/// Run the enabled lints over generated notes (sorted by `time_us`).
+pub(crate) fn lint_notes(
+    config: &LintConfig,
+    notes: &[Note],
+    step_times: &[Microseconds],
+    track: &[TrackLine],
+) -> Vec<CompileWarning> {
+    let steps = Steps {
+        times: step_times,
+        lines: track
+            .iter()
+            .filter_map(|l| match l {
+                TrackLine::Step { line, .. } => Some(*line),
+                TrackLine::Directive { .. } => None,
+            })
+            .collect(),
+    };
+
+    let mut out = Vec::new();
+    if let Some(max) = config.max_keys_with_scratch {
+        chords_with_scratch(&steps, notes, max, &mut out);
+    }
+    if let Some(nps) = config.max_jack_nps {
+        jacks(&steps, notes, nps, &mut out);
+    }
+    if config.full_keyboard_cn {
+        full_keyboard_cn(&steps, notes, &mut out);
+    }
+    out.sort_by_key(|w| (w.time_us, w.code));
+    out
+}
+
+fn chords_with_scratch(steps: &Steps, notes: &[Note], max: usize, out: &mut Vec<CompileWarning>) {
+    for chord in notes.chunk_by(|a, b| a.time_us == b.time_us) {
+        let scratch = chord.iter().any(|n| n.col == 0);
+        let keys = chord.iter().filter(|n| n.col != 0).count();
+        if scratch && keys > max {
+            out.push(steps.warning(
+                "W5001",
+                format!("{keys} keys together with scratch (max {max})"),
+                chord[0].time_us,
+                None,
+            ));
+        }
+    }
+}
+
+/// One warning per run of too-fast repeats on a lane, at the first offending note.
+fn jacks(steps: &Steps, notes: &[Note], max_nps: f64, out: &mut Vec<CompileWarning>) {
+    let min_gap_us = 1_000_000.0 / max_nps;
+    let mut last: [Option<Microseconds>; 8] = [None; 8];
+    let mut in_run = [false; 8];
+
+    for n in notes {
+        let col = n.col as usize;
+        if col >= 8 {
+            continue;
+        }
+        let too_fast = last[col].is_some_and(|prev| ((n.time_us - prev) as f64) < min_gap_us);
+        if too_fast && !in_run[col] {
+            let gap = n.time_us - last[col].unwrap_or(0);
+            out.push(steps.warning(
+                "W5002",
+                format!(
+                    "lane={col} repeats after {gap}us ({:.1} notes/sec, max {max_nps})",
+                    1_000_000.0 / gap as f64
+                ),
+                n.time_us,
+                Some(n.col),
+            ));
+        }
+        in_run[col] = too_fast;
+        last[col] = Some(n.time_us);
+    }
+}
+
+fn full_keyboard_cn(steps: &Steps, notes: &[Note], out: &mut Vec<CompileWarning>) {
+    // (time, delta): ends sort before starts at the same time, so back-to-back CNs don't overlap
+    let mut edges: Vec<(Microseconds, i32)> = Vec::new();
+    for n in notes.iter().filter(|n| n.col != 0) {
+        if let NoteKind::ChargeNote { end_time_us } | NoteKind::HellChargeNote { end_time_us } =
+            n.kind
+        {
+            edges.push((n.time_us, 1));
+            edges.push((end_time_us, -1));
+        }
+    }
+    edges.sort_unstable();
+
+    let mut held = 0;
+    for (time_us, delta) in edges {
+        held += delta;
+        if delta > 0 && held == 7 {
+            out.push(steps.warning(
+                "W5003",
+                "charge notes held on all 7 key lanes at once".to_string(),
+                time_us,
+                None,
+            ));
+        }
+    }
+}
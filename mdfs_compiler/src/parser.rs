@@ -1,20 +1,26 @@
+use std::collections::HashMap;
+
 use crate::CompileError;
 
 #[derive(Debug, Default, Clone)]
-pub(crate) struct ParsedMeta {
-    pub(crate) title: Option<String>,
-    pub(crate) artist: Option<String>,
-    pub(crate) version: Option<String>,
-    pub(crate) tags: Vec<String>,
-    pub(crate) sound_manifest: Option<String>,
+pub(crate) struct ParsedMeta<'a> {
+    pub(crate) title: Option<&'a str>,
+    pub(crate) artist: Option<&'a str>,
+    pub(crate) version: Option<&'a str>,
+    pub(crate) tags: Vec<&'a str>,
+    pub(crate) sound_manifest: Option<&'a str>,
     pub(crate) sound_manifest_line: Option<usize>,
 }
 
+/// Parsed source. Text fields borrow from the `.mdfs` source; owned `String`s are only
+/// created when the chart is generated.
 #[derive(Debug, Clone)]
-pub(crate) struct ParsedMdfs {
-    pub(crate) meta: ParsedMeta,
+pub(crate) struct ParsedMdfs<'a> {
+    pub(crate) meta: ParsedMeta<'a>,
     pub(crate) meta_line: usize,
     pub(crate) track: Vec<TrackLine>,
+    /// Distinct sound_ids referenced by the track, indexed by `SoundRef`.
+    pub(crate) sound_ids: Vec<&'a str>,
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +41,10 @@ pub(crate) enum TrackLine {
 pub(crate) enum Directive {
     Bpm(f64),
     Div(u32),
+    /// Suggested scroll rate from the next step on.
+    ScrollHint(f64),
+    /// Default keysound for notes on `lane` until changed; `None` (`-`) clears it.
+    LaneSound { lane: usize, sound: Option<SoundRef> },
 }
 
 #[derive(Debug, Clone, Default)]
@@ -43,23 +53,42 @@ pub(crate) struct RevSpec {
     pub(crate) at: Vec<usize>,
 }
 
+/// Index into `ParsedMdfs.sound_ids`.
+pub(crate) type SoundRef = u32;
+
 #[derive(Debug, Clone)]
 pub(crate) enum SoundSpec {
     None,
-    Single(String),
-    PerLane([Option<String>; 8]),
+    Single(SoundRef),
+    PerLane([Option<SoundRef>; 8]),
+}
+
+/// Interns sound_ids so steps carry a small index instead of the id text.
+#[derive(Default)]
+struct SoundInterner<'a> {
+    ids: Vec<&'a str>,
+    index: HashMap<&'a str, SoundRef>,
 }
 
-pub(crate) fn parse_mdfs(src: &str) -> Result<ParsedMdfs, CompileError> {
+impl<'a> SoundInterner<'a> {
+    fn intern(&mut self, id: &'a str) -> SoundRef {
+        *self.index.entry(id).or_insert_with(|| {
+            self.ids.push(id);
+            (self.ids.len() - 1) as SoundRef
+        })
+    }
+}
+
+pub(crate) fn parse_mdfs(src: &str) -> Result<ParsedMdfs<'_>, CompileError> {
     let mut meta = ParsedMeta::default();
     let mut track = Vec::new();
+    let mut sounds = SoundInterner::default();
     let mut in_track = false;
     let mut meta_line = 1;
 
-    for (i, raw_line) in src.lines().enumerate() {
+    for (i, line) in SourceLines::new(src).enumerate() {
         let line_no = i + 1;
-        let line = strip_inline_comment(raw_line);
-        let trimmed = line.trim();
+        let trimmed = trim(line);
         if trimmed.is_empty() {
             continue;
         }
@@ -106,7 +135,7 @@ pub(crate) fn parse_mdfs(src: &str) -> Result<ParsedMdfs, CompileError> {
                     line_no,
                 ));
             }
-            if let Some(d) = parse_track_directive(trimmed, line_no)? {
+            if let Some(d) = parse_track_directive(trimmed, line_no, &mut sounds)? {
                 track.push(TrackLine::Directive {
                     line: line_no,
                     directive: d,
@@ -121,7 +150,7 @@ pub(crate) fn parse_mdfs(src: &str) -> Result<ParsedMdfs, CompileError> {
             ));
         }
 
-        let step = parse_step_line(trimmed, line_no)?;
+        let step = parse_step_line(trimmed, line_no, &mut sounds)?;
         track.push(step);
     }
 
@@ -133,19 +162,20 @@ pub(crate) fn parse_mdfs(src: &str) -> Result<ParsedMdfs, CompileError> {
         meta,
         meta_line,
         track,
+        sound_ids: sounds.ids,
     })
 }
 
-fn parse_header_directive(
-    meta: &mut ParsedMeta,
-    trimmed: &str,
+fn parse_header_directive<'a>(
+    meta: &mut ParsedMeta<'a>,
+    trimmed: &'a str,
     line_no: usize,
 ) -> Result<(), CompileError> {
     let (name, rest) = split_directive(trimmed, line_no)?;
     match name {
-        "title" => meta.title = Some(rest.to_string()),
-        "artist" => meta.artist = Some(rest.to_string()),
-        "version" => meta.version = Some(rest.to_string()),
+        "title" => meta.title = Some(rest),
+        "artist" => meta.artist = Some(rest),
+        "version" => meta.version = Some(rest),
         "tags" => meta.tags = parse_tags_csv(rest, line_no)?,
         "sound_manifest" => {
             if meta.sound_manifest.is_some() {
@@ -158,7 +188,7 @@ fn parse_header_directive(
             if rest.is_empty() {
                 return Err(CompileError::new("E2001", "missing manifest path", line_no));
             }
-            meta.sound_manifest = Some(rest.to_string());
+            meta.sound_manifest = Some(rest);
             meta.sound_manifest_line = Some(line_no);
         }
         _ => {
@@ -172,7 +202,11 @@ fn parse_header_directive(
     Ok(())
 }
 
-fn parse_track_directive(trimmed: &str, line_no: usize) -> Result<Option<Directive>, CompileError> {
+fn parse_track_directive<'a>(
+    trimmed: &'a str,
+    line_no: usize,
+    sounds: &mut SoundInterner<'a>,
+) -> Result<Option<Directive>, CompileError> {
     let (name, rest) = split_directive(trimmed, line_no)?;
     match name {
         "bpm" => {
@@ -193,14 +227,46 @@ fn parse_track_directive(trimmed: &str, line_no: usize) -> Result<Option<Directi
             }
             Ok(Some(Directive::Div(div as u32)))
         }
+        "scroll_hint" => {
+            let rate: f64 = rest
+                .parse()
+                .map_err(|_| CompileError::new("E3006", "invalid @scroll_hint", line_no))?;
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(CompileError::new("E3006", "@scroll_hint must be a finite value > 0", line_no));
+            }
+            Ok(Some(Directive::ScrollHint(rate)))
+        }
+        "lane_sound" => {
+            let invalid = || {
+                CompileError::new(
+                    "E1007",
+                    format!(
+                        "invalid @lane_sound, expected @lane_sound <0-7> <sound_id|-> (context={trimmed})"
+                    ),
+                    line_no,
+                )
+                .with_context(trimmed.to_string())
+            };
+            let mut parts = rest.split_whitespace();
+            let (Some(lane), Some(id), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(invalid());
+            };
+            let lane: usize = lane.parse().ok().filter(|&l| l < 8).ok_or_else(invalid)?;
+            let sound = (id != "-").then(|| sounds.intern(id));
+            Ok(Some(Directive::LaneSound { lane, sound }))
+        }
         _ => Ok(None),
     }
 }
 
-fn parse_step_line(trimmed: &str, line_no: usize) -> Result<TrackLine, CompileError> {
+fn parse_step_line<'a>(
+    trimmed: &'a str,
+    line_no: usize,
+    sounds: &mut SoundInterner<'a>,
+) -> Result<TrackLine, CompileError> {
     let (cells, tail) = parse_step_cells_and_tail(trimmed, line_no)?;
     validate_step_cells(&cells, trimmed, line_no)?;
-    let (sound, rev) = parse_step_tail(tail, trimmed, line_no)?;
+    let (sound, rev) = parse_step_tail(tail, trimmed, line_no, sounds)?;
 
     Ok(TrackLine::Step {
         line: line_no,
@@ -214,6 +280,14 @@ fn parse_step_cells_and_tail(
     trimmed: &str,
     line_no: usize,
 ) -> Result<([char; 8], &str), CompileError> {
+    if let Some(head) = trimmed
+        .as_bytes()
+        .first_chunk::<8>()
+        .filter(|h| h.is_ascii())
+    {
+        return Ok((head.map(char::from), trim(&trimmed[8..])));
+    }
+
     let mut chars = trimmed.chars();
     let mut cells = ['.'; 8];
     for idx in 0..8 {
@@ -232,6 +306,16 @@ fn parse_step_cells_and_tail(
 }
 
 fn validate_step_cells(cells: &[char; 8], context_line: &str, line_no: usize) -> Result<(), CompileError> {
+    // checked here first so valid steps skip the per-cell error paths below
+    let valid = |(idx, &ch): (usize, &char)| match ch {
+        '.' | 'N' => true,
+        'S' | 'b' | 'm' | 'B' | 'M' | '!' => idx == 0,
+        'l' | 'h' => idx != 0,
+        _ => false,
+    };
+    if cells.iter().enumerate().all(valid) {
+        return Ok(());
+    }
     for (idx, &ch) in cells.iter().enumerate() {
         validate_step_cell(idx, ch, context_line, line_no)?;
     }
@@ -305,10 +389,11 @@ fn validate_step_cell(idx: usize, ch: char, context_line: &str, line_no: usize)
     Ok(())
 }
 
-fn parse_step_tail(
-    tail: &str,
+fn parse_step_tail<'a>(
+    tail: &'a str,
     context_line: &str,
     line_no: usize,
+    sounds: &mut SoundInterner<'a>,
 ) -> Result<(SoundSpec, RevSpec), CompileError> {
     if tail.is_empty() {
         return Ok((SoundSpec::None, RevSpec::default()));
@@ -317,13 +402,13 @@ fn parse_step_tail(
     let mut sound = SoundSpec::None;
     let mut rev = RevSpec::default();
 
-    let mut rest = tail.trim();
-    if let Some(colon_idx) = rest.find(':') {
-        let after = rest[(colon_idx + 1)..].trim();
+    let mut rest = trim(tail);
+    if let Some(colon_idx) = rest.bytes().position(|b| b == b':') {
+        let after = trim(&rest[(colon_idx + 1)..]);
         // split sound and rev directives (if any)
         let (sound_part, rev_part) = split_sound_and_rev(after);
-        sound = parse_sound_spec(sound_part.trim(), context_line, line_no)?;
-        rest = rev_part.trim();
+        sound = parse_sound_spec(sound_part, context_line, line_no, sounds)?;
+        rest = trim(rev_part);
     }
 
     if !rest.is_empty() {
@@ -334,14 +419,15 @@ fn parse_step_tail(
 }
 
 fn split_sound_and_rev(after_colon: &str) -> (&str, &str) {
-    let rev_every = after_colon.find("@rev_every");
-    let rev_at = after_colon.find("@rev_at");
-    let idx = match (rev_every, rev_at) {
-        (Some(a), Some(b)) => Some(a.min(b)),
-        (Some(a), None) => Some(a),
-        (None, Some(b)) => Some(b),
-        (None, None) => None,
-    };
+    // first "@rev_every" / "@rev_at"; scanning for '@' avoids two substring searches per step
+    let idx = after_colon
+        .bytes()
+        .enumerate()
+        .filter(|&(_, b)| b == b'@')
+        .map(|(i, _)| i)
+        .find(|&i| {
+            after_colon[i..].starts_with("@rev_every") || after_colon[i..].starts_with("@rev_at")
+        });
 
     match idx {
         Some(i) => (&after_colon[..i], &after_colon[i..]),
@@ -477,8 +563,13 @@ fn split_first_token(s: &str) -> (&str, &str) {
     }
 }
 
-fn parse_sound_spec(s: &str, context_line: &str, line_no: usize) -> Result<SoundSpec, CompileError> {
-    let s = s.trim();
+fn parse_sound_spec<'a>(
+    s: &'a str,
+    context_line: &str,
+    line_no: usize,
+    sounds: &mut SoundInterner<'a>,
+) -> Result<SoundSpec, CompileError> {
+    let s = trim(s);
     if s.is_empty() {
         return Ok(SoundSpec::None);
     }
@@ -488,7 +579,7 @@ fn parse_sound_spec(s: &str, context_line: &str, line_no: usize) -> Result<Sound
     }
 
     if s.starts_with('[') {
-        return parse_sound_array(s, context_line, line_no);
+        return parse_sound_array(s, context_line, line_no, sounds);
     }
 
     if s.contains(char::is_whitespace) {
@@ -501,10 +592,15 @@ fn parse_sound_spec(s: &str, context_line: &str, line_no: usize) -> Result<Sound
             .with_context(context_line.to_string()),
         );
     }
-    Ok(SoundSpec::Single(s.to_string()))
+    Ok(SoundSpec::Single(sounds.intern(s)))
 }
 
-fn parse_sound_array(s: &str, context_line: &str, line_no: usize) -> Result<SoundSpec, CompileError> {
+fn parse_sound_array<'a>(
+    s: &'a str,
+    context_line: &str,
+    line_no: usize,
+    sounds: &mut SoundInterner<'a>,
+) -> Result<SoundSpec, CompileError> {
     if !s.ends_with(']') {
         return Err(
             CompileError::new(
@@ -516,8 +612,16 @@ fn parse_sound_array(s: &str, context_line: &str, line_no: usize) -> Result<Soun
         );
     }
     let inner = &s[1..s.len() - 1];
-    let parts: Vec<&str> = inner.split(',').map(|p| p.trim()).collect();
-    if parts.len() != 8 {
+    // `[',']` rather than `','`: the single-char searcher's memchr setup dominates on these short slots
+    let mut slots = [""; 8];
+    let mut slot_count = 0;
+    for part in inner.split([',']) {
+        if let Some(slot) = slots.get_mut(slot_count) {
+            *slot = trim(part);
+        }
+        slot_count += 1;
+    }
+    if slot_count != 8 {
         return Err(
             CompileError::new(
                 "E1002",
@@ -527,8 +631,8 @@ fn parse_sound_array(s: &str, context_line: &str, line_no: usize) -> Result<Soun
             .with_context(context_line.to_string()),
         );
     }
-    let mut lanes: [Option<String>; 8] = std::array::from_fn(|_| None);
-    for (i, p) in parts.iter().enumerate() {
+    let mut lanes: [Option<SoundRef>; 8] = [None; 8];
+    for (i, (lane, p)) in lanes.iter_mut().zip(slots).enumerate() {
         if p.is_empty() {
             return Err(
                 CompileError::new(
@@ -540,16 +644,14 @@ fn parse_sound_array(s: &str, context_line: &str, line_no: usize) -> Result<Soun
                 .with_context(context_line.to_string()),
             );
         }
-        if *p == "-" {
-            lanes[i] = None;
-        } else {
-            lanes[i] = Some((*p).to_string());
+        if p != "-" {
+            *lane = Some(sounds.intern(p));
         }
     }
     Ok(SoundSpec::PerLane(lanes))
 }
 
-fn parse_tags_csv(s: &str, line_no: usize) -> Result<Vec<String>, CompileError> {
+fn parse_tags_csv(s: &str, line_no: usize) -> Result<Vec<&str>, CompileError> {
     let s = s.trim();
     if s.is_empty() {
         return Ok(vec![]);
@@ -564,7 +666,7 @@ fn parse_tags_csv(s: &str, line_no: usize) -> Result<Vec<String>, CompileError>
                 line_no,
             ));
         }
-        tags.push(t.to_string());
+        tags.push(t);
     }
     Ok(tags)
 }
@@ -580,9 +682,61 @@ fn split_directive(trimmed: &str, line_no: usize) -> Result<(&str, &str), Compil
     Ok((name, rest))
 }
 
-fn strip_inline_comment(line: &str) -> &str {
-    match line.find('#') {
-        Some(i) => &line[..i],
-        None => line,
+/// `str::trim` with an ASCII fast path; chart lines are almost always ASCII.
+fn trim(s: &str) -> &str {
+    let t = s.trim_ascii();
+    match (t.as_bytes().first(), t.as_bytes().last()) {
+        (Some(&a), Some(&b)) if !a.is_ascii() || !b.is_ascii() => t.trim(),
+        _ => t,
+    }
+}
+
+/// `str::lines()` with inline `#` comments removed.
+///
+/// The next `#` in the source is found once and reused until the line that contains it,
+/// instead of searching every line.
+struct SourceLines<'a> {
+    src: &'a str,
+    pos: usize,
+    next_hash: Option<usize>,
+}
+
+impl<'a> SourceLines<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            next_hash: src.find('#'),
+        }
+    }
+}
+
+impl<'a> Iterator for SourceLines<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let bytes = self.src.as_bytes();
+        let start = self.pos;
+        if start >= bytes.len() {
+            return None;
+        }
+        // lines are short, so a plain scan beats memchr's setup cost
+        let nl = bytes[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| start + i);
+        self.pos = nl + 1;
+
+        let mut end = nl;
+        if let Some(h) = self.next_hash.filter(|&h| h < nl) {
+            end = h;
+            self.next_hash = self.src[self.pos.min(bytes.len())..]
+                .find('#')
+                .map(|i| self.pos + i);
+        } else if end > start && bytes[end - 1] == b'\r' {
+            end -= 1;
+        }
+        // `\n`, `\r` and `#` are ASCII, so both ends are char boundaries
+        Some(&self.src[start..end])
     }
 }
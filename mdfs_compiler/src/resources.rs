@@ -1,28 +1,91 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use mdf_schema::SoundSlice;
+use tracing::{debug, info};
 
 use crate::{CompileError, CompileOptions};
 use crate::parser::ParsedMdfs;
 
+/// Contents of a sound manifest: sound_id -> file, plus the region to play for sliced entries.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Resources {
+    pub(crate) files: HashMap<String, String>,
+    pub(crate) slices: HashMap<String, SoundSlice>,
+}
+
+type ManifestResult = Result<Resources, CompileError>;
+
+/// Manifests shared between charts compiled together, keyed by resolved path.
+///
+/// Each manifest is read and parsed once even when several threads ask for it.
+#[derive(Default)]
+pub(crate) struct ManifestCache(Mutex<HashMap<PathBuf, Arc<OnceLock<ManifestResult>>>>);
+
+#[allow(clippy::result_large_err)]
 pub(crate) fn load_resources(
     parsed: &ParsedMdfs,
     options: &CompileOptions,
-) -> Result<HashMap<String, String>, CompileError> {
-    let Some(manifest_path) = &parsed.meta.sound_manifest else {
-        return Ok(HashMap::new());
+    cache: Option<&ManifestCache>,
+) -> ManifestResult {
+    if let Some(inline) = &options.inline_resources {
+        return inline_resources(inline, parsed);
+    }
+
+    let Some(manifest_path) = parsed.meta.sound_manifest else {
+        return Ok(Resources::default());
     };
 
     let manifest_line = parsed.meta.sound_manifest_line.unwrap_or(parsed.meta_line);
 
-    let Some(base_dir) = &options.base_dir else {
-        return Err(CompileError::new(
-            "E2001",
-            "@sound_manifest requires compile_file() or CompileOptions.base_dir",
-            manifest_line,
-        ));
+    let full = match (&options.base_dir, &options.file_loader) {
+        (Some(base_dir), _) => base_dir.join(manifest_path),
+        (None, Some(_)) => PathBuf::from(manifest_path),
+        (None, None) => {
+            return Err(CompileError::new(
+                "E2001",
+                "@sound_manifest requires compile_file() or CompileOptions.base_dir",
+                manifest_line,
+            ));
+        }
+    };
+
+    let Some(cache) = cache else {
+        return load_manifest(&full, manifest_line, options);
     };
+    let slot = cache
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(full.clone())
+        .or_default()
+        .clone();
+    let mut loaded_here = false;
+    let result = slot.get_or_init(|| {
+        loaded_here = true;
+        load_manifest(&full, manifest_line, options)
+    });
+    if !loaded_here {
+        debug!(path = %full.display(), "sound manifest served from cache");
+    }
+    match result {
+        Ok(map) => Ok(map.clone()),
+        Err(e) => {
+            // report against this chart's @sound_manifest line
+            let mut e = e.clone();
+            e.line = manifest_line;
+            Err(e)
+        }
+    }
+}
 
-    let full = base_dir.join(manifest_path);
-    let bytes = fs::read(&full).map_err(|e| {
+#[allow(clippy::result_large_err)]
+fn load_manifest(full: &Path, manifest_line: usize, options: &CompileOptions) -> ManifestResult {
+    let bytes = read_file(full, options).map_err(|e| {
         CompileError::new(
             "E2001",
             format!("failed to read manifest {}: {e}", full.display()),
@@ -36,21 +99,88 @@ pub(crate) fn load_resources(
             .with_file(full.display().to_string())
     })?;
 
-    let mut out = HashMap::new();
+    let invalid = |message: String| {
+        CompileError::new("E2003", message, manifest_line).with_file(full.display().to_string())
+    };
+
+    let mut out = Resources::default();
     for (k, v) in map {
-        let Some(s) = v.as_str() else {
-            return Err(
-                CompileError::new("E2003", "manifest values must be strings", manifest_line)
-                    .with_file(full.display().to_string()),
-            );
+        let (file, slice) = match &v {
+            serde_json::Value::String(s) => (s.as_str(), None),
+            serde_json::Value::Object(_) => {
+                let (file, slice) = parse_slice(&v).ok_or_else(|| {
+                    invalid(format!(
+                        "invalid slice for {k:?}: expected {{\"file\", \"start_ms\", \"len_ms\" > 0}}"
+                    ))
+                })?;
+                (file, Some(slice))
+            }
+            _ => {
+                return Err(invalid(
+                    "manifest values must be strings or slice objects".to_string(),
+                ));
+            }
         };
-        if k.trim().is_empty() || s.trim().is_empty() {
-            return Err(
-                CompileError::new("E2003", "manifest keys/values must be non-empty", manifest_line)
-                    .with_file(full.display().to_string()),
-            );
+        if k.trim().is_empty() || file.trim().is_empty() {
+            return Err(invalid("manifest keys/values must be non-empty".to_string()));
+        }
+        if let Some(slice) = slice {
+            out.slices.insert(k.clone(), slice);
         }
-        out.insert(k, s.to_string());
+        out.files.insert(k, file.to_string());
     }
+    info!(
+        path = %full.display(),
+        entries = out.files.len(),
+        slices = out.slices.len(),
+        "loaded sound manifest"
+    );
     Ok(out)
 }
+
+#[allow(clippy::result_large_err)]
+fn inline_resources(inline: &HashMap<String, String>, parsed: &ParsedMdfs) -> ManifestResult {
+    if inline.iter().any(|(k, v)| k.trim().is_empty() || v.trim().is_empty()) {
+        return Err(CompileError::new(
+            "E2003",
+            "CompileOptions.inline_resources keys/values must be non-empty",
+            parsed.meta.sound_manifest_line.unwrap_or(parsed.meta_line),
+        ));
+    }
+    debug!(entries = inline.len(), "using inline resources");
+    Ok(Resources {
+        files: inline.clone(),
+        slices: HashMap::new(),
+    })
+}
+
+/// `{"file": "bgm.ogg", "start_ms": 1234, "len_ms": 350}`; unknown keys are rejected.
+fn parse_slice(v: &serde_json::Value) -> Option<(&str, SoundSlice)> {
+    let obj = v.as_object()?;
+    if obj.keys().any(|k| !matches!(k.as_str(), "file" | "start_ms" | "len_ms")) {
+        return None;
+    }
+    let file = obj.get("file")?.as_str()?;
+    let start_ms = obj.get("start_ms")?.as_u64()?;
+    let len_ms = obj.get("len_ms")?.as_u64().filter(|&n| n > 0)?;
+    Some((file, SoundSlice { start_ms, len_ms }))
+}
+
+fn read_file(path: &Path, options: &CompileOptions) -> io::Result<Vec<u8>> {
+    if let Some(loader) = &options.file_loader {
+        return loader.load(path);
+    }
+
+    #[cfg(feature = "fs")]
+    {
+        std::fs::read(path)
+    }
+
+    #[cfg(not(feature = "fs"))]
+    {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "built without the `fs` feature; set CompileOptions.file_loader",
+        ))
+    }
+}
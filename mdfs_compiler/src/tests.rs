@@ -4,10 +4,10 @@ use crate::{
     parser::{RevSpec, SoundSpec, TrackLine},
 };
 use mdf_schema::{Microseconds, NoteKind};
+use std::{collections::HashMap, path::PathBuf};
+#[cfg(feature = "fs")]
 use std::{
-    collections::HashMap,
     fs,
-    path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -47,6 +47,26 @@ track: |
     assert!(chart.meta.total_duration_us > 0);
 }
 
+#[test]
+fn crlf_comments_and_unicode_whitespace_are_skipped() {
+    let src = "@title T # trailing\r\n# full line\r\n@artist A\r\n@version 2.2\r\ntrack: |\r\n  @bpm 120\r\n  @div 4\r\n  ..N..... # kick\r\n\u{3000}.l.N....\u{3000}\r\n  .lN.....";
+
+    let chart = compile_str(src).unwrap();
+    assert_eq!(chart.meta.title, "T");
+    let notes: Vec<(Microseconds, u8, &NoteKind)> =
+        chart.notes.iter().map(|n| (n.time_us, n.col, &n.kind)).collect();
+    assert_eq!(
+        notes,
+        vec![
+            (0, 2, &NoteKind::Tap),
+            // a tap and a hold starting together keep tap-first order
+            (500_000, 3, &NoteKind::Tap),
+            (500_000, 1, &NoteKind::ChargeNote { end_time_us: 1_000_000 }),
+            (1_000_000, 2, &NoteKind::Tap),
+        ]
+    );
+}
+
 #[test]
 fn mss_generates_reverse_checkpoints_from_markers_and_rev_at() {
     let src = r#"
@@ -77,6 +97,7 @@ track: |
     }
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn compile_with_manifest_loads_resources_and_validates_sound_ids() {
     let tmp_base = std::env::temp_dir().join(format!(
@@ -114,6 +135,7 @@ track: |
         src,
         CompileOptions {
             base_dir: Some(tmp_base.clone()),
+            ..Default::default()
         },
     )
     .unwrap();
@@ -125,6 +147,7 @@ track: |
     assert_eq!(chart.bgm_events[0].sound_id, "SE_END");
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn repo_example_compiles() {
     let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -134,6 +157,7 @@ fn repo_example_compiles() {
     assert!(!chart.notes.is_empty());
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn repo_mixed_long_example_compiles_and_generates_expected_kinds() {
     let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -174,6 +198,7 @@ fn repo_mixed_long_example_compiles_and_generates_expected_kinds() {
     assert!(chart.bgm_events.iter().any(|e| e.sound_id == "SE_END"));
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn error_code_missing_input_file_is_e2001_with_file_field() {
     let missing = PathBuf::from("this_file_should_not_exist_oxidizer_test_12345.mdfs");
@@ -294,6 +319,7 @@ track: |
     assert!(err.message.contains("lane=2"));
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn error_code_sound_id_missing_in_manifest_is_e2101_with_sound_id_and_lane() {
     let tmp_base = std::env::temp_dir().join(format!(
@@ -313,6 +339,7 @@ fn error_code_sound_id_missing_in_manifest_is_e2101_with_sound_id_and_lane() {
         src,
         CompileOptions {
             base_dir: Some(tmp_base.clone()),
+            ..Default::default()
         },
     )
     .unwrap_err();
@@ -383,7 +410,7 @@ fn error_code_e4004_tap_then_hold_start_same_time_lane() {
     let step_times: Vec<Microseconds> = vec![0, 0];
     let resources = HashMap::<String, String>::new();
 
-    let err = pass2_generate(&track, &step_times, &resources).unwrap_err();
+    let err = pass2_generate(&track, &step_times, &[], &resources).unwrap_err();
     assert_eq!(err.code, "E4004");
     assert_eq!(err.kind, CompileErrorKind::Validation);
     assert_eq!(err.step_index, Some(1));
@@ -423,7 +450,7 @@ fn error_code_e4004_hold_start_then_tap_same_time_lane() {
     let step_times: Vec<Microseconds> = vec![0, 0];
     let resources = HashMap::<String, String>::new();
 
-    let err = pass2_generate(&track, &step_times, &resources).unwrap_err();
+    let err = pass2_generate(&track, &step_times, &[], &resources).unwrap_err();
     assert_eq!(err.code, "E4004");
     assert_eq!(err.kind, CompileErrorKind::Validation);
     assert_eq!(err.step_index, Some(1));
@@ -438,6 +465,79 @@ fn error_code_e4004_hold_start_then_tap_same_time_lane() {
     assert!(err.message.contains("overlaps"));
 }
 
+#[test]
+fn error_code_e4103_hold_end_not_after_start() {
+    let mut cells = ['.'; 8];
+    cells[2] = 'l';
+
+    let track = vec![
+        TrackLine::Step {
+            line: 1,
+            cells,
+            sound: SoundSpec::None,
+            rev: RevSpec::default(),
+        },
+        TrackLine::Step {
+            line: 2,
+            cells,
+            sound: SoundSpec::None,
+            rev: RevSpec::default(),
+        },
+    ];
+
+    let step_times: Vec<Microseconds> = vec![100, 100];
+    let resources = HashMap::<String, String>::new();
+
+    let err = pass2_generate(&track, &step_times, &[], &resources).unwrap_err();
+    assert_eq!(err.code, "E4103");
+    assert_eq!(err.kind, CompileErrorKind::Validation);
+    assert_eq!(err.line, 2);
+    assert_eq!(err.step_index, Some(1));
+    assert_eq!(err.time_us, Some(100));
+    assert_eq!(err.lane, Some(2));
+}
+
+#[test]
+fn error_code_e4104_mss_checkpoint_outside_hold_span() {
+    let mut mss = ['.'; 8];
+    mss[0] = 'm';
+
+    let track = vec![
+        TrackLine::Step {
+            line: 1,
+            cells: mss,
+            sound: SoundSpec::None,
+            rev: RevSpec {
+                every: Some(1),
+                at: Vec::new(),
+            },
+        },
+        TrackLine::Step {
+            line: 2,
+            cells: ['.'; 8],
+            sound: SoundSpec::None,
+            rev: RevSpec::default(),
+        },
+        TrackLine::Step {
+            line: 3,
+            cells: mss,
+            sound: SoundSpec::None,
+            rev: RevSpec::default(),
+        },
+    ];
+
+    // step 1 lands on the hold start, so its checkpoint is not strictly inside
+    let step_times: Vec<Microseconds> = vec![0, 0, 100];
+    let resources = HashMap::<String, String>::new();
+
+    let err = pass2_generate(&track, &step_times, &[], &resources).unwrap_err();
+    assert_eq!(err.code, "E4104");
+    assert_eq!(err.kind, CompileErrorKind::Validation);
+    assert_eq!(err.line, 3);
+    assert_eq!(err.time_us, Some(0));
+    assert_eq!(err.lane, Some(0));
+}
+
 #[test]
 fn error_code_missing_bpm_before_steps_is_e3001() {
     let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @div 4\n  ..N.....\n";
@@ -466,6 +566,64 @@ fn error_code_missing_div_before_steps_is_e3002() {
     assert_eq!(err.lane, None);
 }
 
+#[test]
+fn scroll_hint_compiles_into_suggested_speed_events() {
+    let src = r#"
+@title T
+@artist A
+@version 2.2
+track: |
+  @bpm 120
+  @div 4
+  ..N.....
+  @scroll_hint 2.0
+  ..N.....
+  @scroll_hint 1.5
+  @scroll_hint 0.5
+  ..N.....
+"#;
+
+    let chart = compile_str(src).unwrap();
+    let events: Vec<(u64, f64, bool)> = chart
+        .speed_events
+        .iter()
+        .map(|e| (e.time_us, e.scroll_rate, e.suggested))
+        .collect();
+    // track start at 1.0, hint at step 1, last hint on step 2 wins
+    assert_eq!(
+        events,
+        vec![(0, 1.0, true), (500_000, 2.0, true), (1_000_000, 0.5, true)]
+    );
+}
+
+#[test]
+fn scroll_hint_before_first_step_sets_initial_rate() {
+    let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @scroll_hint 3\n  @bpm 120\n  @div 4\n  ..N.....\n  @scroll_hint 1\n";
+    let chart = compile_str(src).unwrap();
+    let events: Vec<(u64, f64)> = chart
+        .speed_events
+        .iter()
+        .map(|e| (e.time_us, e.scroll_rate))
+        .collect();
+    // the trailing hint has no step to attach to
+    assert_eq!(events, vec![(0, 3.0)]);
+    assert_eq!(chart.meta.total_duration_us, 0);
+}
+
+#[test]
+fn error_code_invalid_scroll_hint_is_e3006() {
+    for value in ["0", "-1", "fast", "inf"] {
+        let src = format!(
+            "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  @scroll_hint {value}\n  ..N.....\n"
+        );
+        let err = compile_str(&src).unwrap_err();
+        assert_eq!(err.code, "E3006", "value {value}");
+        assert_eq!(err.kind, CompileErrorKind::TimeMap);
+        assert_eq!(err.line, 7);
+    }
+}
+
+#[cfg(feature = "fs")]
 #[test]
 fn error_code_invalid_manifest_json_is_e2002() {
     let tmp_base = std::env::temp_dir().join(format!(
@@ -485,6 +643,7 @@ fn error_code_invalid_manifest_json_is_e2002() {
         src,
         CompileOptions {
             base_dir: Some(tmp_base.clone()),
+            ..Default::default()
         },
     )
     .unwrap_err();
@@ -504,6 +663,7 @@ fn error_code_invalid_manifest_json_is_e2002() {
     assert_eq!(err.start_time_us, None);
 }
 
+#[cfg(feature = "fs")]
 #[test]
 fn error_code_invalid_manifest_values_is_e2003() {
     let tmp_base = std::env::temp_dir().join(format!(
@@ -523,6 +683,7 @@ fn error_code_invalid_manifest_values_is_e2003() {
         src,
         CompileOptions {
             base_dir: Some(tmp_base.clone()),
+            ..Default::default()
         },
     )
     .unwrap_err();
@@ -542,6 +703,125 @@ fn error_code_invalid_manifest_values_is_e2003() {
     assert_eq!(err.start_time_us, None);
 }
 
+#[test]
+fn lane_sound_sets_default_keysound_per_lane() {
+    let src = r#"
+@title T
+@artist A
+@version 2.2
+@sound_manifest sounds.json
+track: |
+  @bpm 120
+  @div 4
+  @lane_sound 1 K01
+  @lane_sound 0 S01
+  SN......
+  .N...... : K02
+  SNN..... : [-,-,K02,-,-,-,-,-]
+  @lane_sound 1 -
+  .l......
+  .l......
+"#;
+    let chart = compile_str_with_options(
+        src,
+        CompileOptions {
+            file_loader: Some(FileLoader::new(|_| {
+                Ok(br#"{"K01":"k1.wav","K02":"k2.wav","S01":"s.wav"}"#.to_vec())
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let sounds: Vec<(u64, u8, Option<&str>)> = chart
+        .notes
+        .iter()
+        .map(|n| (n.time_us, n.col, n.sound_id.as_deref()))
+        .collect();
+    assert_eq!(
+        sounds,
+        vec![
+            (0, 0, Some("S01")),
+            (0, 1, Some("K01")),
+            (500_000, 1, Some("K02")),
+            (1_000_000, 0, Some("S01")),
+            (1_000_000, 1, Some("K01")),
+            (1_000_000, 2, Some("K02")),
+            (1_500_000, 1, None),
+        ]
+    );
+}
+
+#[test]
+fn error_code_invalid_lane_sound_is_e1007() {
+    for directive in ["@lane_sound 8 K01", "@lane_sound 1", "@lane_sound x K01", "@lane_sound 1 K01 K02"] {
+        let src = format!(
+            "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  {directive}\n  .N......\n"
+        );
+        let err = compile_str(&src).unwrap_err();
+        assert_eq!(err.code, "E1007", "{directive}");
+        assert_eq!(err.kind, CompileErrorKind::Parse);
+        assert_eq!(err.line, 7);
+        assert_eq!(err.context.as_deref(), Some(directive));
+    }
+}
+
+#[test]
+fn error_code_lane_sound_without_manifest_is_e2101_on_directive_line() {
+    let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  @lane_sound 3 K01\n  ........\n";
+    let err = compile_str(src).unwrap_err();
+    assert_eq!(err.code, "E2101");
+    assert_eq!(err.line, 7);
+    assert_eq!(err.lane, Some(3));
+}
+
+#[allow(clippy::result_large_err)]
+fn compile_with_manifest(manifest: &'static str) -> Result<MdfChart, CompileError> {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ..N..... : K01\n";
+    compile_str_with_options(
+        src,
+        CompileOptions {
+            file_loader: Some(FileLoader::new(move |_| Ok(manifest.as_bytes().to_vec()))),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn manifest_slice_entries_become_resource_slices() {
+    let chart = compile_with_manifest(
+        r#"{"K01":{"file":"bgm.ogg","start_ms":1234,"len_ms":350},"SE":"se.wav"}"#,
+    )
+    .unwrap();
+    assert_eq!(chart.resources.get("K01").map(String::as_str), Some("bgm.ogg"));
+    assert_eq!(chart.resources.get("SE").map(String::as_str), Some("se.wav"));
+    assert_eq!(chart.resource_slices.len(), 1);
+    assert_eq!(
+        chart.resource_slices.get("K01"),
+        Some(&mdf_schema::SoundSlice {
+            start_ms: 1234,
+            len_ms: 350
+        })
+    );
+    assert_eq!(chart.notes[0].sound_id.as_deref(), Some("K01"));
+}
+
+#[test]
+fn error_code_invalid_manifest_slice_is_e2003() {
+    for manifest in [
+        r#"{"K01":{"file":"bgm.ogg","start_ms":0}}"#,
+        r#"{"K01":{"file":"bgm.ogg","start_ms":0,"len_ms":0}}"#,
+        r#"{"K01":{"file":"bgm.ogg","start_ms":-5,"len_ms":10}}"#,
+        r#"{"K01":{"file":"","start_ms":0,"len_ms":10}}"#,
+        r#"{"K01":{"file":"bgm.ogg","start_ms":0,"len_ms":10,"gain":1}}"#,
+        r#"{"K01":["bgm.ogg"]}"#,
+    ] {
+        let err = compile_with_manifest(manifest).unwrap_err();
+        assert_eq!(err.code, "E2003", "{manifest}");
+        assert_eq!(err.line, 4);
+    }
+}
+
 #[test]
 fn error_code_multiple_sound_manifest_is_e2004() {
     let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest a.json\n@sound_manifest b.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n";
@@ -833,3 +1113,296 @@ fn parse_error_e4003_bang_on_non_scratch_includes_lane_context() {
     assert!(err.message.contains("lane=1"));
     assert!(err.message.contains("context=.!......"));
 }
+
+#[test]
+fn file_loader_supplies_manifest_without_base_dir() {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest packs/sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ..N..... : K01\n";
+
+    let chart = compile_str_with_options(
+        src,
+        CompileOptions {
+            file_loader: Some(FileLoader::new(|path| {
+                assert_eq!(norm_path(&path.display().to_string()), "packs/sounds.json");
+                Ok(br#"{"K01":"kick.wav"}"#.to_vec())
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(chart.resources.get("K01").map(String::as_str), Some("kick.wav"));
+    assert_eq!(chart.notes[0].sound_id.as_deref(), Some("K01"));
+}
+
+#[test]
+fn file_loader_receives_path_joined_with_base_dir() {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n";
+
+    let chart = compile_str_with_options(
+        src,
+        CompileOptions {
+            base_dir: Some(PathBuf::from("virtual").join("song")),
+            file_loader: Some(FileLoader::new(|path| {
+                assert_eq!(norm_path(&path.display().to_string()), "virtual/song/sounds.json");
+                Ok(b"{}".to_vec())
+            })),
+            lints: None,
+            emit_analysis: false,
+            inline_resources: None,
+        },
+    )
+    .unwrap();
+
+    assert!(chart.resources.is_empty());
+}
+
+#[test]
+fn file_loader_error_is_e2001_with_file_field() {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n";
+
+    let err = compile_str_with_options(
+        src,
+        CompileOptions {
+            file_loader: Some(FileLoader::new(|_| {
+                Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no such entry"))
+            })),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err.code, "E2001");
+    assert_eq!(err.kind, CompileErrorKind::IO);
+    assert_eq!(err.line, 4);
+    assert_path_ends_with(err.file.as_deref(), "sounds.json");
+    assert!(err.message.contains("no such entry"));
+}
+
+#[cfg(not(feature = "fs"))]
+#[test]
+fn manifest_without_fs_or_file_loader_is_e2001_unsupported() {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n";
+
+    let err = compile_str_with_options(
+        src,
+        CompileOptions {
+            base_dir: Some(PathBuf::from("song")),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err.code, "E2001");
+    assert_eq!(err.kind, CompileErrorKind::IO);
+    assert_eq!(err.line, 4);
+    assert_path_ends_with(err.file.as_deref(), "sounds.json");
+    assert!(err.message.contains("built without the `fs` feature"), "{}", err.message);
+}
+
+#[test]
+fn inline_resources_replace_manifest_without_file_access() {
+    let src = "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ..N..... : K01\n";
+
+    let chart = compile_str_with_options(
+        src,
+        CompileOptions {
+            file_loader: Some(FileLoader::new(|path| {
+                panic!("unexpected read of {}", path.display())
+            })),
+            inline_resources: Some(HashMap::from([("K01".to_string(), "kick.wav".to_string())])),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(chart.resources.get("K01").map(String::as_str), Some("kick.wav"));
+    assert_eq!(chart.notes[0].sound_id.as_deref(), Some("K01"));
+
+    // no @sound_manifest line: the inline map is still used and sound ids are checked against it
+    let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  ..N..... : K02\n";
+    let err = compile_str_with_options(
+        src,
+        CompileOptions {
+            inline_resources: Some(HashMap::from([("K01".to_string(), "kick.wav".to_string())])),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err.code, "E2101");
+}
+
+#[test]
+fn inline_resources_reject_empty_entries() {
+    let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n";
+
+    let err = compile_str_with_options(
+        src,
+        CompileOptions {
+            inline_resources: Some(HashMap::from([("K01".to_string(), " ".to_string())])),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err.code, "E2003");
+    assert_eq!(err.line, 4);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn compile_many_keeps_order_and_reads_shared_manifest_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let tmp_base = std::env::temp_dir().join(format!(
+        "oxidizer_mdfs_compiler_test_compile_many_{}_{}",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    fs::create_dir_all(&tmp_base).unwrap();
+
+    let mut paths = Vec::new();
+    for i in 0..6 {
+        let path = tmp_base.join(format!("chart{i}.mdfs"));
+        let src = format!("@title T{i}\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ..N..... : K01\n");
+        fs::write(&path, src).unwrap();
+        paths.push(path);
+    }
+    paths.insert(2, tmp_base.join("missing.mdfs"));
+
+    let loads = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&loads);
+    let options = CompileOptions {
+        file_loader: Some(FileLoader::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(br#"{"K01":"kick.wav"}"#.to_vec())
+        })),
+        ..Default::default()
+    };
+
+    let results = compile_many(&paths, &options);
+    assert_eq!(results.len(), 7);
+    let titles: Vec<Option<&str>> = results
+        .iter()
+        .map(|r| r.as_ref().ok().map(|c| c.meta.title.as_str()))
+        .collect();
+    assert_eq!(
+        titles,
+        vec![Some("T0"), Some("T1"), None, Some("T2"), Some("T3"), Some("T4"), Some("T5")]
+    );
+    assert_eq!(results[2].as_ref().unwrap_err().code, "E2001");
+    assert_eq!(results[0].as_ref().unwrap().resources.get("K01").map(String::as_str), Some("kick.wav"));
+    assert_eq!(loads.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn compile_many_reports_shared_manifest_error_on_each_charts_line() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "oxidizer_mdfs_compiler_test_compile_many_err_{}_{}",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    fs::create_dir_all(&tmp_base).unwrap();
+    fs::write(tmp_base.join("sounds.json"), "{ not json").unwrap();
+
+    let a = tmp_base.join("a.mdfs");
+    let b = tmp_base.join("b.mdfs");
+    fs::write(&a, "@title T\n@artist A\n@version 2.2\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n").unwrap();
+    fs::write(&b, "@title T\n@artist A\n@version 2.2\n\n\n@sound_manifest sounds.json\ntrack: |\n  @bpm 120\n  @div 4\n  ........\n").unwrap();
+
+    let results = compile_many(&[a, b], &CompileOptions::default());
+    let a_err = results[0].as_ref().unwrap_err();
+    let b_err = results[1].as_ref().unwrap_err();
+    assert_eq!((a_err.code, a_err.line), ("E2002", 4));
+    assert_eq!((b_err.code, b_err.line), ("E2002", 6));
+}
+
+fn lint_src(body: &str) -> String {
+    format!("@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 16\n{body}")
+}
+
+fn warnings_for(src: &str, lints: Option<LintConfig>) -> Vec<CompileWarning> {
+    let options = CompileOptions {
+        lints,
+        ..Default::default()
+    };
+    compile_str_with_warnings(src, options).unwrap().1
+}
+
+#[test]
+fn lint_scratch_with_too_many_keys_is_w5001() {
+    let src = lint_src("  ........\n  SNNNNNN.\n  SNNNNN..\n");
+    let warnings = warnings_for(&src, Some(LintConfig::default()));
+    assert_eq!(warnings.len(), 1);
+    let w = &warnings[0];
+    assert_eq!(w.code, "W5001");
+    assert_eq!(w.line, 8);
+    assert_eq!(w.step_index, 1);
+    assert_eq!(w.time_us, 125_000);
+    assert_eq!(w.lane, None);
+}
+
+#[test]
+fn lint_fast_jack_is_w5002_once_per_run() {
+    // 16ths at 120bpm are 8 notes/sec
+    let src = lint_src("  .N......\n  .N......\n  .N......\n  ........\n  .N......\n");
+    let config = LintConfig {
+        max_jack_nps: Some(6.0),
+        ..Default::default()
+    };
+    let warnings = warnings_for(&src, Some(config));
+    let jacks: Vec<(usize, Option<u8>)> = warnings
+        .iter()
+        .filter(|w| w.code == "W5002")
+        .map(|w| (w.step_index, w.lane))
+        .collect();
+    assert_eq!(jacks, vec![(1, Some(1))]);
+
+    assert!(warnings_for(&src, Some(LintConfig::default())).is_empty());
+}
+
+#[test]
+fn lint_cn_on_every_key_lane_is_w5003() {
+    let src = lint_src("  .lllllll\n  ........\n  .lllllll\n  .l......\n  .l......\n");
+    let warnings = warnings_for(&src, Some(LintConfig::default()));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].code, "W5003");
+    assert_eq!(warnings[0].step_index, 0);
+    assert_eq!(warnings[0].line, 7);
+}
+
+#[test]
+fn lints_disabled_by_default() {
+    let src = lint_src("  SNNNNNNN\n");
+    assert!(warnings_for(&src, None).is_empty());
+    assert_eq!(warnings_for(&src, Some(LintConfig::default()))[0].code, "W5001");
+}
+
+#[test]
+fn emit_analysis_reports_density_and_bpm_timeline() {
+    // 4 steps per second at 120bpm/div 8; the 150 between steps is overridden by 180
+    let src = "@title T\n@artist A\n@version 2.2\ntrack: |\n  @bpm 120\n  @div 8\n  .N......\n  .NN.....\n  ........\n  ........\n  @bpm 120\n  @bpm 150\n  @bpm 180\n  ...N....\n";
+
+    assert!(compile_str(src).unwrap().analysis.is_none());
+
+    let options = CompileOptions {
+        emit_analysis: true,
+        ..Default::default()
+    };
+    let chart = compile_str_with_options(src, options).unwrap();
+    let analysis = chart.analysis.unwrap();
+    assert_eq!(analysis.density_curve, vec![3, 1]);
+    let bpms: Vec<(u64, f64)> = analysis
+        .bpm_timeline
+        .iter()
+        .map(|p| (p.time_us, p.bpm))
+        .collect();
+    assert_eq!(bpms, vec![(0, 120.0), (1_000_000, 180.0)]);
+}
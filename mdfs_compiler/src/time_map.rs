@@ -1,4 +1,4 @@
-use mdf_schema::Microseconds;
+use mdf_schema::{Microseconds, SpeedEvent};
 
 use crate::CompileError;
 use crate::parser::{Directive, TrackLine};
@@ -9,21 +9,37 @@ pub(crate) fn pass1_time_map(
     let mut bpm: Option<f64> = None;
     let mut div: Option<u32> = None;
     let mut current_time_us: Microseconds = 0;
-    let mut starts = Vec::new();
-    let mut durs = Vec::new();
+    let mut starts = Vec::with_capacity(track.len());
+    let mut durs = Vec::with_capacity(track.len());
+    // recomputed only after @bpm/@div changes
+    let mut cached_dur: Option<Microseconds> = None;
 
     for line in track {
         match line {
             TrackLine::Directive { line: _line, directive } => match directive {
-                Directive::Bpm(v) => bpm = Some(*v),
-                Directive::Div(v) => div = Some(*v),
+                Directive::Bpm(v) => {
+                    bpm = Some(*v);
+                    cached_dur = None;
+                }
+                Directive::Div(v) => {
+                    div = Some(*v);
+                    cached_dur = None;
+                }
+                Directive::ScrollHint(_) | Directive::LaneSound { .. } => {}
             },
             TrackLine::Step { line, .. } => {
-                let bpm = bpm
-                    .ok_or_else(|| CompileError::new("E3001", "@bpm is required before step lines", *line))?;
-                let div = div
-                    .ok_or_else(|| CompileError::new("E3002", "@div is required before step lines", *line))?;
-                let dur = step_duration_us(bpm, div, *line)?;
+                let dur = match cached_dur {
+                    Some(dur) => dur,
+                    None => {
+                        let bpm = bpm.ok_or_else(|| {
+                            CompileError::new("E3001", "@bpm is required before step lines", *line)
+                        })?;
+                        let div = div.ok_or_else(|| {
+                            CompileError::new("E3002", "@div is required before step lines", *line)
+                        })?;
+                        *cached_dur.insert(step_duration_us(bpm, div, *line)?)
+                    }
+                };
                 starts.push(current_time_us);
                 durs.push(dur);
                 current_time_us = current_time_us
@@ -35,6 +51,51 @@ pub(crate) fn pass1_time_map(
     Ok((starts, durs))
 }
 
+/// Place `@scroll_hint` directives on the time map as suggested speed events.
+///
+/// A hint takes effect at the start of the next step and is dropped if no step
+/// follows; a later hint at the same time replaces an earlier one. When the first
+/// hint comes after time 0, a 1.0 event is emitted for the track start.
+pub(crate) fn scroll_hints(track: &[TrackLine], starts: &[Microseconds]) -> Vec<SpeedEvent> {
+    let mut events: Vec<SpeedEvent> = Vec::new();
+    let mut step = 0;
+
+    for line in track {
+        match line {
+            TrackLine::Directive {
+                directive: Directive::ScrollHint(rate),
+                ..
+            } => {
+                let Some(&time_us) = starts.get(step) else {
+                    break;
+                };
+                match events.last_mut() {
+                    Some(last) if last.time_us == time_us => last.scroll_rate = *rate,
+                    _ => events.push(SpeedEvent {
+                        time_us,
+                        scroll_rate: *rate,
+                        suggested: true,
+                    }),
+                }
+            }
+            TrackLine::Directive { .. } => {}
+            TrackLine::Step { .. } => step += 1,
+        }
+    }
+
+    if events.first().is_some_and(|e| e.time_us > 0) {
+        events.insert(
+            0,
+            SpeedEvent {
+                time_us: 0,
+                scroll_rate: 1.0,
+                suggested: true,
+            },
+        );
+    }
+    events
+}
+
 fn step_duration_us(bpm: f64, div: u32, line: usize) -> Result<Microseconds, CompileError> {
     if !(bpm > 0.0) {
         return Err(CompileError::new("E3003", "@bpm must be > 0", line));